@@ -1,36 +1,112 @@
 use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
 
-pub fn update_lockfile(repository_path: &str) -> bool {
+const POLL_INTERVAL_MILLIS: u64 = 100;
+
+/// Runs a command to completion, killing it once the timeout has passed.
+///
+/// Only the command itself is killed, processes it started may keep running.
+pub fn run(command: &mut Command, timeout: Option<Duration>) -> bool {
+    let mut child = match command.spawn() {
+        Ok(child) => child,
+        Err(_) => return false,
+    };
+
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return child.wait().map(|s| s.success()).unwrap_or(false),
+    };
+
+    let start = Instant::now();
+    loop {
+        match child.try_wait() {
+            Ok(Some(status)) => return status.success(),
+            Ok(None) => {},
+            Err(_) => return false,
+        }
+
+        if start.elapsed() >= timeout {
+            let _ = child.kill();
+            let _ = child.wait();
+            return false;
+        }
+
+        thread::sleep(Duration::from_millis(POLL_INTERVAL_MILLIS));
+    }
+}
+
+pub fn update_lockfile(repository_path: &str, timeout: Option<Duration>) -> bool {
     let manifest_path = format!("{}/Cargo.toml", repository_path);
-    Command::new("cargo")
+    let mut command = Command::new("cargo");
+    command
         .arg("fetch")
         .arg("--manifest-path")
-        .arg(manifest_path)
-        .status()
-        .map(|s| s.success())
-        .unwrap_or(false)
+        .arg(manifest_path);
+    run(&mut command, timeout)
+}
+
+/// The environment variable cargo reads the token of a named registry from.
+pub fn registry_token_variable(registry: &str) -> String {
+    format!("CARGO_REGISTRIES_{}_TOKEN", registry.to_uppercase().replace("-", "_"))
 }
 
-pub fn package(repository_path: &str) -> bool {
+/// Flags semantic-rs sets itself, which must not be passed through.
+const RESERVED_ARGS: [&'static str; 3] = ["--manifest-path", "--token", "--registry"];
+
+/// Makes sure extra arguments don't override the ones semantic-rs passes to cargo.
+pub fn check_extra_args(args: &[String]) -> Result<(), String> {
+    for arg in args {
+        for reserved in RESERVED_ARGS.iter() {
+            if &arg[..] == *reserved || arg.starts_with(&format!("{}=", reserved)) {
+                return Err(format!("`{}` is set by semantic-rs and can't be passed to cargo", reserved));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+pub fn package(repository_path: &str, registry: Option<&str>, extra_args: &[String], timeout: Option<Duration>) -> bool {
     let manifest_path = format!("{}/Cargo.toml", repository_path);
-    Command::new("cargo")
+    let mut command = Command::new("cargo");
+    command
         .arg("package")
         .arg("--manifest-path")
-        .arg(manifest_path)
-        .status()
-        .map(|s| s.success())
-        .unwrap_or(false)
+        .arg(manifest_path);
+    if let Some(registry) = registry {
+        command.arg("--registry").arg(registry);
+    }
+    command.args(extra_args);
+    run(&mut command, timeout)
 }
 
-pub fn publish(repository_path: &str, token: &str) -> bool {
+pub fn publish(repository_path: &str, token: &str, registry: Option<&str>, extra_args: &[String], timeout: Option<Duration>) -> bool {
     let manifest_path = format!("{}/Cargo.toml", repository_path);
-    Command::new("cargo")
+    let mut command = Command::new("cargo");
+    command
         .arg("publish")
         .arg("--manifest-path")
         .arg(manifest_path)
         .arg("--token")
-        .arg(token)
-        .status()
-        .map(|s| s.success())
-        .unwrap_or(false)
+        .arg(token);
+    if let Some(registry) = registry {
+        command.arg("--registry").arg(registry);
+    }
+    command.args(extra_args);
+    run(&mut command, timeout)
+}
+
+#[test]
+fn registry_token_variable_follows_cargo_naming() {
+    assert_eq!("CARGO_REGISTRIES_MY_REGISTRY_TOKEN", registry_token_variable("my-registry"));
+}
+
+#[test]
+fn extra_args_must_not_override_reserved_ones() {
+    let args = |list: &[&str]| list.iter().map(|a| a.to_string()).collect::<Vec<_>>();
+
+    assert!(check_extra_args(&args(&["--no-verify", "--features", "x"])).is_ok());
+    assert!(check_extra_args(&args(&["--manifest-path", "other/Cargo.toml"])).is_err());
+    assert!(check_extra_args(&args(&["--token=secret"])).is_err());
 }
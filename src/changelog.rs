@@ -1,42 +1,200 @@
-use std::io::BufWriter;
-use clog::Clog;
-use clog::fmt::MarkdownWriter;
+use std::io::{BufWriter, Read, Write};
+use std::fs::File;
+use clog::{Clog, SectionMap};
+use clog::fmt::{FormatWriter, MarkdownWriter};
+use clog::git::Commit;
+use git2::{Oid, Repository};
 use std::path::PathBuf;
 
-pub fn write(repository_path: &str, old_version: &str, new_version: &str) -> Result<(), String> {
+/// The changelog file before and after prepending the entry for the new version.
+///
+/// A missing changelog is created, so it starts out empty.
+pub fn preview(repository_path: &str, from: &str, version_heading: &str, ignore_merges: bool) -> Result<(String, String), String> {
     let mut clog = try!(Clog::with_dir(repository_path).map_err(|_| "Clog failed".to_owned()));
 
+    clog
+        .from(from)
+        .version(version_heading);
+
+    let entry = try!(render_entry(&clog, repository_path, ignore_merges));
+
+    let mut content = String::new();
+    if let Ok(mut file) = File::open(changelog_file(repository_path)) {
+        try!(file.read_to_string(&mut content).map_err(|_| "Failed to read Changelog.md".to_owned()));
+    }
+
+    let new_content = format!("{}\n\n\n{}", entry, content);
+    Ok((content, new_content))
+}
+
+pub fn write(repository_path: &str, from: &str, version_heading: &str, ignore_merges: bool) -> Result<(), String> {
+    let (_, new_content) = try!(preview(repository_path, from, version_heading, ignore_merges));
+
+    File::create(changelog_file(repository_path))
+        .and_then(|mut file| file.write_all(new_content.as_bytes()))
+        .map_err(|_| "Failed to write Changelog.md".to_owned())
+}
+
+fn changelog_file(repository_path: &str) -> PathBuf {
     let mut clog_file = PathBuf::from(repository_path);
     clog_file.push("Changelog.md");
+    clog_file
+}
+
+pub fn generate(repository_path: &str, from: &str, version_heading: &str, ignore_merges: bool) -> Result<String, String> {
+    let mut clog = try!(Clog::with_dir(repository_path).map_err(|_| "Clog failed".to_owned()));
 
-    // TODO: Make this configurable? Rely on clog's own configuration?
-    clog.changelog(clog_file.to_str().unwrap())
-        .from(format!("v{}", old_version))
-        .version(format!("v{}", new_version));
+    clog
+        .from(from)
+        .version(version_heading);
 
-    clog.write_changelog().map_err(|_| "Failed to write Changelog.md".to_owned())
+    render(&clog, repository_path, ignore_merges)
 }
 
-pub fn generate(repository_path: &str, old_version: &str, new_version: &str) -> Result<String, String> {
+/// Generates the changelog for an arbitrary range of revisions, without a new version.
+pub fn generate_range(repository_path: &str, from: &str, to: &str, ignore_merges: bool) -> Result<String, String> {
     let mut clog = try!(Clog::with_dir(repository_path).map_err(|_| "Clog failed".to_owned()));
 
     clog
-        .from(format!("v{}", old_version))
-        .version(format!("v{}", new_version));
+        .from(from)
+        .to(to)
+        .version(to);
+
+    render(&clog, repository_path, ignore_merges)
+}
+
+/// The commits clog found in the range, without merge commits if they are ignored.
+fn commits(clog: &Clog, repository_path: &str, ignore_merges: bool) -> Result<Vec<Commit>, String> {
+    let commits = clog.get_commits();
+    if !ignore_merges {
+        return Ok(commits);
+    }
 
+    let repo = try!(Repository::open(repository_path).map_err(|_| "Opening the repository failed".to_owned()));
+    let is_merge = |commit: &Commit| Oid::from_str(&commit.hash)
+        .and_then(|oid| repo.find_commit(oid))
+        .map(|commit| commit.parent_ids().count() > 1)
+        .unwrap_or(false);
+
+    Ok(commits.into_iter().filter(|commit| !is_merge(commit)).collect())
+}
+
+/// The changelog entry as written to the file, starting with an anchor for the version.
+fn render_entry(clog: &Clog, repository_path: &str, ignore_merges: bool) -> Result<String, String> {
+    let section_map = SectionMap::from_commits(try!(commits(clog, repository_path, ignore_merges)));
     let mut out_buf = BufWriter::new(Vec::new());
 
     {
         let mut writer = MarkdownWriter::new(&mut out_buf);
-        try!(clog.write_changelog_with(&mut writer)
+        try!(writer.write_changelog(clog, &section_map)
              .map_err(|_| "Genearting changelog failed"))
     }
 
     let out_buf = out_buf.into_inner().unwrap();
-    let changelog = String::from_utf8(out_buf).unwrap();
+    let entry = String::from_utf8(out_buf).unwrap();
+    Ok(replace_heading(&entry, &clog.version))
+}
+
+/// Replaces the heading clog wrote on the second line, after the anchor.
+///
+/// clog always appends the current date in parentheses, which is up to the heading template instead.
+fn replace_heading(entry: &str, heading: &str) -> String {
+    let mut lines = entry.splitn(3, '\n');
+    let anchor = lines.next().unwrap_or("");
+    let level = lines.next().and_then(|line| line.split(' ').next()).unwrap_or("##");
+    let rest = lines.next().unwrap_or("");
+
+    format!("{}\n{} {}\n{}", anchor, level, heading, rest)
+}
+
+fn render(clog: &Clog, repository_path: &str, ignore_merges: bool) -> Result<String, String> {
+    let changelog = try!(render_entry(clog, repository_path, ignore_merges));
 
     match changelog.find('\n') {
         Some(newline_offset) => Ok(changelog[newline_offset+1..].into()),
         None => Ok(changelog)
     }
 }
+
+/// The level of a markdown heading line, e.g. 2 for `## v1.0.0`.
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if level > 0 && line[level..].starts_with(' ') {
+        Some(level)
+    } else {
+        None
+    }
+}
+
+/// Whether the version appears in the text on its own, so `1.2.3` matches neither `11.2.3` nor `1.2.30`.
+fn mentions_version(text: &str, version: &str) -> bool {
+    let is_version_char = |c: char| c.is_alphanumeric() || c == '.' || c == '-' || c == '+';
+
+    text.match_indices(version).any(|(start, _)| {
+        let before = text[..start].chars().rev().next();
+        let after = text[start + version.len()..].chars().next();
+        // A leading `v` as in `v1.2.3` is fine.
+        before.map_or(true, |c| c == 'v' || !is_version_char(c)) &&
+            after.map_or(true, |c| !is_version_char(c))
+    })
+}
+
+/// Extracts the section of a changelog written for the given version.
+///
+/// The section starts after the first heading mentioning the version and ends
+/// at the next heading of the same or a higher level.
+pub fn extract_section(changelog: &str, version: &str) -> Option<String> {
+    let mut lines = changelog.lines();
+
+    let level = loop {
+        let line = match lines.next() {
+            Some(line) => line,
+            None => return None,
+        };
+        if let Some(level) = heading_level(line) {
+            if mentions_version(line, version) {
+                break level;
+            }
+        }
+    };
+
+    let section = lines
+        .take_while(|line| heading_level(line).map_or(true, |l| l > level))
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    Some(section.trim().to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{extract_section, replace_heading};
+
+    const CHANGELOG: &'static str = "# Changelog\n\n## [1.2.30] - 2024-02-01\n\nNot this one\n\n\
+                                     ## v1.2.3 (2024-01-01)\n\n#### Features\n\n* Something new\n\n\
+                                     ## v1.2.2 (2023-12-01)\n\nOlder\n";
+
+    #[test]
+    fn replaces_heading_with_date() {
+        let entry = "<a name=\"[1.2.3] - 2024-01-01\"></a>\n## [1.2.3] - 2024-01-01 (2024-01-01)\n\n\n#### Features\n";
+        assert_eq!("<a name=\"[1.2.3] - 2024-01-01\"></a>\n## [1.2.3] - 2024-01-01\n\n\n#### Features\n",
+                   replace_heading(entry, "[1.2.3] - 2024-01-01"));
+    }
+
+    #[test]
+    fn extracts_section_of_version() {
+        let section = extract_section(CHANGELOG, "1.2.3").unwrap();
+        assert_eq!("#### Features\n\n* Something new", section);
+    }
+
+    #[test]
+    fn extracts_last_section() {
+        assert_eq!(Some("Older".to_owned()), extract_section(CHANGELOG, "1.2.2"));
+    }
+
+    #[test]
+    fn missing_version() {
+        assert_eq!(None, extract_section(CHANGELOG, "1.2"));
+        assert_eq!(None, extract_section(CHANGELOG, "2.0.0"));
+    }
+}
@@ -0,0 +1,224 @@
+use std::env;
+
+/// Environment variables CI services set on pull request builds.
+///
+/// With a value, the variable has to be set to exactly that value.
+/// Without one, any value except `false` indicates a pull request.
+const PULL_REQUEST_VARIABLES: [(&'static str, Option<&'static str>); 3] = [
+    ("TRAVIS_PULL_REQUEST", None),
+    ("GITHUB_EVENT_NAME", Some("pull_request")),
+    ("CI_PIPELINE_SOURCE", Some("merge_request_event")),
+];
+
+/// A CI service semantic-rs knows how to read the build context from.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Provider {
+    GitHubActions,
+    GitLab,
+    Travis,
+    CircleCi,
+    AzurePipelines,
+    /// Any other service setting `CI`.
+    Generic,
+}
+
+use self::Provider::*;
+
+/// The variable each known service sets to identify itself.
+const PROVIDER_VARIABLES: [(&'static str, Provider); 5] = [
+    ("GITHUB_ACTIONS", GitHubActions),
+    ("GITLAB_CI", GitLab),
+    ("TRAVIS", Travis),
+    ("CIRCLECI", CircleCi),
+    ("TF_BUILD", AzurePipelines),
+];
+
+impl Provider {
+    pub fn name(&self) -> &'static str {
+        match *self {
+            GitHubActions => "GitHub Actions",
+            GitLab => "GitLab CI",
+            Travis => "Travis CI",
+            CircleCi => "CircleCI",
+            AzurePipelines => "Azure Pipelines",
+            Generic => "an unknown CI service",
+        }
+    }
+
+    /// The variable holding the name of the branch being built.
+    fn branch_variable(&self) -> Option<&'static str> {
+        match *self {
+            GitHubActions => Some("GITHUB_REF_NAME"),
+            GitLab => Some("CI_COMMIT_BRANCH"),
+            Travis => Some("TRAVIS_BRANCH"),
+            CircleCi => Some("CIRCLE_BRANCH"),
+            AzurePipelines => Some("BUILD_SOURCEBRANCHNAME"),
+            Generic => None,
+        }
+    }
+
+    /// The variable holding the number of the current build.
+    fn build_number_variable(&self) -> Option<&'static str> {
+        match *self {
+            GitHubActions => Some("GITHUB_RUN_NUMBER"),
+            GitLab => Some("CI_PIPELINE_IID"),
+            Travis => Some("TRAVIS_BUILD_NUMBER"),
+            CircleCi => Some("CIRCLE_BUILD_NUM"),
+            AzurePipelines => Some("BUILD_BUILDID"),
+            Generic => None,
+        }
+    }
+
+    /// The commit message directive that keeps the service from building the release commit.
+    pub fn skip_ci_token(&self) -> &'static str {
+        match *self {
+            AzurePipelines => "***NO_CI***",
+            _ => "[skip ci]",
+        }
+    }
+}
+
+fn detect_with<F>(lookup: F) -> Option<Provider>
+    where F: Fn(&str) -> Option<String>
+{
+    PROVIDER_VARIABLES.iter()
+        .find(|&&(name, _)| matches(lookup(name), None))
+        .map(|&(_, provider)| provider)
+        .or_else(|| if matches(lookup("CI"), None) { Some(Generic) } else { None })
+}
+
+/// The CI service we are running on, if any.
+pub fn detect() -> Option<Provider> {
+    detect_with(|name| env::var(name).ok())
+}
+
+fn branch_with<F>(provider: Provider, lookup: F) -> Option<String>
+    where F: Fn(&str) -> Option<String>
+{
+    provider.branch_variable()
+        .and_then(|name| lookup(name))
+        .and_then(|branch| if branch.is_empty() { None } else { Some(branch) })
+}
+
+/// The branch the CI service is building, which may differ from a detached HEAD.
+pub fn branch(provider: Provider) -> Option<String> {
+    branch_with(provider, |name| env::var(name).ok())
+}
+
+fn build_number_with<F>(provider: Provider, lookup: F) -> Option<String>
+    where F: Fn(&str) -> Option<String>
+{
+    provider.build_number_variable()
+        .and_then(|name| lookup(name))
+        .and_then(|number| if number.is_empty() { None } else { Some(number) })
+}
+
+/// The number of the current build on the CI service.
+pub fn build_number(provider: Provider) -> Option<String> {
+    build_number_with(provider, |name| env::var(name).ok())
+}
+
+/// Parses a `NAME` or `NAME=value` pull request indicator.
+pub fn parse_variable(variable: &str) -> (String, Option<String>) {
+    let mut parts = variable.splitn(2, '=');
+    let name = parts.next().unwrap_or("").to_owned();
+    let value = parts.next().map(|v| v.to_owned());
+    (name, value)
+}
+
+fn matches(actual: Option<String>, expected: Option<&str>) -> bool {
+    match (actual, expected) {
+        (Some(actual), Some(expected)) => actual == expected,
+        (Some(actual), None) => !actual.is_empty() && actual != "false",
+        (None, _) => false,
+    }
+}
+
+fn is_pull_request_with<F>(extra_variables: &[(String, Option<String>)], lookup: F) -> bool
+    where F: Fn(&str) -> Option<String>
+{
+    PULL_REQUEST_VARIABLES.iter()
+        .any(|&(name, value)| matches(lookup(name), value)) ||
+    extra_variables.iter()
+        .any(|&(ref name, ref value)| matches(lookup(name), value.as_ref().map(|v| &v[..])))
+}
+
+/// Whether we are running on a pull request build, based on the known and the configured variables.
+pub fn is_pull_request(extra_variables: &[(String, Option<String>)]) -> bool {
+    is_pull_request_with(extra_variables, |name| env::var(name).ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(vars: &[(&str, &str)], name: &str) -> Option<String> {
+        vars.iter()
+            .find(|&&(var, _)| var == name)
+            .map(|&(_, value)| value.to_owned())
+    }
+
+    #[test]
+    fn detects_known_pull_request_builds() {
+        assert!(is_pull_request_with(&[], |name| lookup(&[("TRAVIS_PULL_REQUEST", "42")], name)));
+        assert!(is_pull_request_with(&[], |name| lookup(&[("GITHUB_EVENT_NAME", "pull_request")], name)));
+        assert!(is_pull_request_with(&[], |name| lookup(&[("CI_PIPELINE_SOURCE", "merge_request_event")], name)));
+    }
+
+    #[test]
+    fn ignores_other_builds() {
+        assert!(!is_pull_request_with(&[], |name| lookup(&[], name)));
+        assert!(!is_pull_request_with(&[], |name| lookup(&[("TRAVIS_PULL_REQUEST", "false")], name)));
+        assert!(!is_pull_request_with(&[], |name| lookup(&[("GITHUB_EVENT_NAME", "push")], name)));
+    }
+
+    #[test]
+    fn detects_providers() {
+        assert_eq!(Some(GitHubActions), detect_with(|name| lookup(&[("CI", "true"), ("GITHUB_ACTIONS", "true")], name)));
+        assert_eq!(Some(GitLab), detect_with(|name| lookup(&[("GITLAB_CI", "true")], name)));
+        assert_eq!(Some(Travis), detect_with(|name| lookup(&[("TRAVIS", "true")], name)));
+        assert_eq!(Some(CircleCi), detect_with(|name| lookup(&[("CIRCLECI", "true")], name)));
+        assert_eq!(Some(AzurePipelines), detect_with(|name| lookup(&[("TF_BUILD", "True")], name)));
+        assert_eq!(Some(Generic), detect_with(|name| lookup(&[("CI", "true")], name)));
+        assert_eq!(None, detect_with(|name| lookup(&[], name)));
+    }
+
+    #[test]
+    fn ignores_disabled_ci_variable() {
+        assert_eq!(None, detect_with(|name| lookup(&[("CI", "false")], name)));
+        assert_eq!(None, detect_with(|name| lookup(&[("CI", "")], name)));
+        assert_eq!(None, detect_with(|name| lookup(&[("TRAVIS", "")], name)));
+    }
+
+    #[test]
+    fn reads_branch_of_provider() {
+        let vars = [("GITHUB_REF_NAME", "main"), ("TRAVIS_BRANCH", "master")];
+        assert_eq!(Some("main".to_string()), branch_with(GitHubActions, |name| lookup(&vars, name)));
+        assert_eq!(Some("master".to_string()), branch_with(Travis, |name| lookup(&vars, name)));
+        assert_eq!(None, branch_with(Generic, |name| lookup(&vars, name)));
+    }
+
+    #[test]
+    fn reads_build_number_of_provider() {
+        let vars = [("GITHUB_RUN_NUMBER", "12"), ("CI_PIPELINE_IID", "34"), ("TRAVIS_BUILD_NUMBER", "56")];
+        assert_eq!(Some("12".to_string()), build_number_with(GitHubActions, |name| lookup(&vars, name)));
+        assert_eq!(Some("34".to_string()), build_number_with(GitLab, |name| lookup(&vars, name)));
+        assert_eq!(None, build_number_with(CircleCi, |name| lookup(&vars, name)));
+        assert_eq!(None, build_number_with(Generic, |name| lookup(&vars, name)));
+    }
+
+    #[test]
+    fn uses_skip_ci_token_of_provider() {
+        assert_eq!("[skip ci]", GitLab.skip_ci_token());
+        assert_eq!("***NO_CI***", AzurePipelines.skip_ci_token());
+    }
+
+    #[test]
+    fn detects_configured_variables() {
+        let extra = [parse_variable("BUILDKITE_PULL_REQUEST"), parse_variable("CUSTOM_EVENT=review")];
+
+        assert!(is_pull_request_with(&extra, |name| lookup(&[("BUILDKITE_PULL_REQUEST", "7")], name)));
+        assert!(is_pull_request_with(&extra, |name| lookup(&[("CUSTOM_EVENT", "review")], name)));
+        assert!(!is_pull_request_with(&extra, |name| lookup(&[("CUSTOM_EVENT", "push")], name)));
+    }
+}
@@ -1,7 +1,8 @@
 use clog::Clog;
 use clog::error::Error;
+use semver::Version;
 
-#[derive(PartialEq,Eq,Debug,PartialOrd,Ord)]
+#[derive(PartialEq,Eq,Debug,PartialOrd,Ord,Clone,Copy)]
 pub enum CommitType {
     Unknown,
     Patch,
@@ -11,43 +12,381 @@ pub enum CommitType {
 
 use self::CommitType::*;
 
-pub fn analyze_single(commit: &str) -> Result<CommitType, Error> {
+impl CommitType {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            Unknown => "none",
+            Patch => "patch",
+            Minor => "minor",
+            Major => "major",
+        }
+    }
+}
+
+/// The commit message conventions a project follows.
+///
+/// They differ in which commit types lead to a release.
+#[derive(PartialEq,Eq,Debug,Clone,Copy)]
+pub enum Preset {
+    /// `feat` and `fix`, as in the conventional commits specification.
+    ConventionalCommits,
+    /// Like conventional commits, but `perf` is released as a patch.
+    Angular,
+    /// Emoji prefixes like `:bug:` as used by Atom, which has none for features.
+    Atom,
+}
+
+use self::Preset::*;
+
+impl Default for Preset {
+    fn default() -> Preset {
+        ConventionalCommits
+    }
+}
+
+impl Preset {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ConventionalCommits => "conventionalcommits",
+            Angular => "angular",
+            Atom => "atom",
+        }
+    }
+
+    /// The type of a commit subject, e.g. `feat` for `feat(parser): ...`.
+    fn commit_type<'a>(&self, subject: &'a str) -> &'a str {
+        match *self {
+            Atom => subject.split_whitespace().next().unwrap_or(""),
+            _ => {
+                let prefix = subject.splitn(2, ':').next().unwrap_or("");
+                prefix.splitn(2, '(').next().unwrap_or("").trim().trim_right_matches('!')
+            }
+        }
+    }
+
+    /// Whether the subject marks a breaking change with a `!` after the type or scope,
+    /// as in `feat!: ...` or `fix(api)!: ...`.
+    fn is_breaking_shorthand(&self, subject: &str) -> bool {
+        if *self == Atom || !subject.contains(':') {
+            return false;
+        }
+
+        let prefix = subject.splitn(2, ':').next().unwrap_or("").trim();
+        prefix.ends_with('!') && !prefix.contains(char::is_whitespace)
+    }
+
+    fn bump_for(&self, commit_type: &str) -> CommitType {
+        match (*self, commit_type) {
+            (Atom, ":bug:") | (Atom, ":racehorse:") | (Atom, ":lock:") | (Atom, ":non-potable_water:") => Patch,
+            (Atom, _) => Unknown,
+            (_, "feat") | (_, "ft") => Minor,
+            (_, "fix") | (_, "fx") => Patch,
+            (Angular, "perf") => Patch,
+            _ => Unknown,
+        }
+    }
+}
+
+/// How bumps are mapped while the major version is 0.
+#[derive(PartialEq,Eq,Debug,Clone,Copy)]
+pub enum ZeroVersion {
+    /// No special handling, a breaking change releases 1.0.0.
+    Standard,
+    /// Breaking changes bump the minor version, like features do.
+    SemverDefault,
+    /// Breaking changes bump the minor version and features only the patch version.
+    Strict,
+}
+
+impl Default for ZeroVersion {
+    fn default() -> ZeroVersion {
+        ZeroVersion::Standard
+    }
+}
+
+impl ZeroVersion {
+    pub fn as_str(&self) -> &'static str {
+        match *self {
+            ZeroVersion::Standard => "standard",
+            ZeroVersion::SemverDefault => "semver-default",
+            ZeroVersion::Strict => "strict",
+        }
+    }
+
+    /// The bump to apply to a 0.x version instead of `bump`.
+    pub fn map_bump(&self, bump: CommitType) -> CommitType {
+        match (*self, bump) {
+            (ZeroVersion::Standard, bump) => bump,
+            (_, Major) => Minor,
+            (ZeroVersion::Strict, Minor) => Patch,
+            (_, bump) => bump,
+        }
+    }
+}
+
+/// Parses a 0.x behavior as given on the command line.
+pub fn parse_zero_version(behavior: &str) -> Option<ZeroVersion> {
+    match &behavior.to_lowercase()[..] {
+        "standard" => Some(ZeroVersion::Standard),
+        "semver-default" => Some(ZeroVersion::SemverDefault),
+        "strict" => Some(ZeroVersion::Strict),
+        _ => None,
+    }
+}
+
+/// Parses a preset name as given on the command line.
+pub fn parse_preset(preset: &str) -> Option<Preset> {
+    match &preset.to_lowercase()[..] {
+        "conventionalcommits" => Some(ConventionalCommits),
+        "angular" => Some(Angular),
+        "atom" => Some(Atom),
+        _ => None,
+    }
+}
+
+/// Parses a bump level as given on the command line.
+pub fn parse_bump(bump: &str) -> Option<CommitType> {
+    match &bump.to_lowercase()[..] {
+        "major" => Some(Major),
+        "minor" => Some(Minor),
+        "patch" => Some(Patch),
+        _ => None,
+    }
+}
+
+/// Whether a commit subject follows the preset's format at all, regardless of its type.
+///
+/// Merge commits are accepted as they are generated by git or the forge.
+pub fn is_conforming_subject(subject: &str, preset: Preset) -> bool {
+    if subject.starts_with("Merge ") {
+        return true;
+    }
+
+    match preset {
+        Atom => {
+            let emoji = preset.commit_type(subject);
+            emoji.len() > 2 && emoji.starts_with(':') && emoji.ends_with(':')
+        },
+        _ => {
+            let mut parts = subject.splitn(2, ':');
+            let prefix = parts.next().unwrap_or("").trim_right_matches('!');
+            let description = parts.next().unwrap_or("").trim();
+            let commit_type = preset.commit_type(subject);
+
+            let scope_closed = match prefix.find('(') {
+                Some(_) => prefix.ends_with(')'),
+                None => true,
+            };
+
+            !commit_type.is_empty() && commit_type.chars().all(|c| c.is_alphanumeric() || c == '-') &&
+                scope_closed && !description.is_empty()
+        },
+    }
+}
+
+fn analyze_subject(subject: &str, preset: Preset) -> CommitType {
+    if preset.is_breaking_shorthand(subject) {
+        return Major;
+    }
+
+    preset.bump_for(preset.commit_type(subject))
+}
+
+/// Removes indentation and list markers, as found in the body of squash merges.
+fn strip_bullet(line: &str) -> &str {
+    line.trim().trim_left_matches(|c| c == '*' || c == '-').trim()
+}
+
+/// Whether any line of the message, not only a trailing footer, announces a breaking change.
+fn has_breaking_footer(commit: &str) -> bool {
+    commit.lines()
+        .map(strip_bullet)
+        .any(|line| line.starts_with("BREAKING CHANGE") || line.starts_with("BREAKING-CHANGE"))
+}
+
+pub fn analyze_single(commit: &str, preset: Preset) -> Result<CommitType, Error> {
     let clog = Clog::new().expect("Clog initialization failed");
-    let commit = clog.parse_raw_commit(commit);
+    let parsed = clog.parse_raw_commit(commit);
 
-    if !commit.breaks.is_empty() {
+    if !parsed.breaks.is_empty() {
         return Ok(Major);
     }
 
-    let commit_type = match &commit.commit_type[..] {
-        "Features" => Minor,
-        "Bug Fixes" => Patch,
-        _ => Unknown,
-    };
+    // The first line is the commit id, the second one the subject.
+    let subject = commit.lines().nth(1).unwrap_or("");
+    Ok(analyze_subject(subject, preset))
+}
 
-    Ok(commit_type)
+/// Like `analyze_single`, but also treats every line of the body as the subject
+/// of a squashed commit and takes the largest bump.
+pub fn analyze_squashed(commit: &str, preset: Preset) -> Result<CommitType, Error> {
+    if has_breaking_footer(commit) {
+        return Ok(Major);
+    }
+
+    let own = try!(analyze_single(commit, preset));
+
+    let squashed = commit.lines()
+        .skip(2)
+        .map(strip_bullet)
+        .filter(|line| !line.is_empty())
+        .map(|line| analyze_subject(line, preset))
+        .max()
+        .unwrap_or(Unknown);
+
+    Ok(::std::cmp::max(own, squashed))
+}
+
+/// Extracts the version from a `Release-As: x.y.z` footer.
+pub fn release_as(commit: &str) -> Option<Version> {
+    commit.lines()
+        .filter_map(|line| {
+            let mut parts = line.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some(key), Some(value)) if key.trim().to_lowercase() == "release-as" => {
+                    Version::parse(value.trim()).ok()
+                },
+                _ => None
+            }
+        })
+        .last()
+}
+
+/// The bump that leads from one version to another, e.g. to a version forced by `Release-As`.
+pub fn bump_between(version: &Version, new_version: &Version) -> CommitType {
+    if new_version.major != version.major {
+        Major
+    } else if new_version.minor != version.minor {
+        Minor
+    } else {
+        Patch
+    }
 }
 
 #[test]
 fn unknown_type() {
     let commit = "0\nThis commit message has no type";
-    assert_eq!(Unknown, analyze_single(commit).unwrap());
+    assert_eq!(Unknown, analyze_single(commit, Preset::default()).unwrap());
 }
 
 #[test]
 fn patch_commit() {
     let commit = "0\nfix: This commit fixes a bug";
-    assert_eq!(Patch, analyze_single(commit).unwrap());
+    assert_eq!(Patch, analyze_single(commit, Preset::default()).unwrap());
 }
 
 #[test]
 fn minor_commit() {
     let commit = "0\nfeat: This commit introduces a new feature";
-    assert_eq!(Minor, analyze_single(commit).unwrap());
+    assert_eq!(Minor, analyze_single(commit, Preset::default()).unwrap());
 }
 
 #[test]
 fn major_commit() {
     let commit = "0\nfeat: This commits breaks something\nBREAKING CHANGE: breaks things";
-    assert_eq!(Major, analyze_single(commit).unwrap());
+    assert_eq!(Major, analyze_single(commit, Preset::default()).unwrap());
+}
+
+#[test]
+fn breaking_shorthand() {
+    assert_eq!(Major, analyze_single("0\nfeat!: Drop the old API", Preset::default()).unwrap());
+    assert_eq!(Major, analyze_single("0\nfix(api)!: Change the return type", Preset::default()).unwrap());
+}
+
+#[test]
+fn no_breaking_shorthand() {
+    assert_eq!(Minor, analyze_single("0\nfeat: Add a new API", Preset::default()).unwrap());
+    assert_eq!(Unknown, analyze_single("0\nWow this works!: finally", Preset::default()).unwrap());
+}
+
+#[test]
+fn breaking_change_in_squash_body() {
+    let commit = "0\nUpdate parser (#12)\n\n* feat: New syntax\n\n  BREAKING CHANGE: old syntax is gone\n\n* fix: Typo";
+    assert_eq!(Major, analyze_squashed(commit, Preset::default()).unwrap());
+}
+
+#[test]
+fn squashed_commits() {
+    let commit = "0\nUpdate parser (#12)\n\n* feat: New syntax\n\n* fix: Typo";
+    assert_eq!(Unknown, analyze_single(commit, Preset::default()).unwrap());
+    assert_eq!(Minor, analyze_squashed(commit, Preset::default()).unwrap());
+}
+
+#[test]
+fn scoped_commit() {
+    let commit = "0\nfeat(parser): Support a new syntax";
+    assert_eq!(Minor, analyze_single(commit, Preset::default()).unwrap());
+}
+
+#[test]
+fn perf_commit_depends_on_preset() {
+    let commit = "0\nperf: Speed things up";
+    assert_eq!(Unknown, analyze_single(commit, ConventionalCommits).unwrap());
+    assert_eq!(Patch, analyze_single(commit, Angular).unwrap());
+}
+
+#[test]
+fn atom_commit() {
+    assert_eq!(Patch, analyze_single("0\n:bug: Fix a crash", Atom).unwrap());
+    assert_eq!(Unknown, analyze_single("0\n:memo: Update docs", Atom).unwrap());
+    assert_eq!(Unknown, analyze_single("0\nfix: Not an atom commit", Atom).unwrap());
+}
+
+#[test]
+fn conforming_subjects() {
+    assert!(is_conforming_subject("feat(parser): Support comments", ConventionalCommits));
+    assert!(is_conforming_subject("refactor!: Drop the old API", ConventionalCommits));
+    assert!(is_conforming_subject("Merge pull request #42 from user/branch", ConventionalCommits));
+    assert!(!is_conforming_subject("Fix the parser", ConventionalCommits));
+    assert!(!is_conforming_subject("fix(parser: Unclosed scope", ConventionalCommits));
+    assert!(!is_conforming_subject("fix:", ConventionalCommits));
+    assert!(is_conforming_subject(":memo: Update docs", Atom));
+    assert!(!is_conforming_subject("docs: Update docs", Atom));
+}
+
+#[test]
+fn zero_version_bumps() {
+    assert_eq!(Major, ZeroVersion::Standard.map_bump(Major));
+    assert_eq!(Minor, ZeroVersion::SemverDefault.map_bump(Major));
+    assert_eq!(Minor, ZeroVersion::SemverDefault.map_bump(Minor));
+    assert_eq!(Minor, ZeroVersion::Strict.map_bump(Major));
+    assert_eq!(Patch, ZeroVersion::Strict.map_bump(Minor));
+    assert_eq!(Unknown, ZeroVersion::Strict.map_bump(Unknown));
+    assert_eq!(Some(ZeroVersion::Strict), parse_zero_version("strict"));
+    assert_eq!(None, parse_zero_version("loose"));
+}
+
+#[test]
+fn parse_presets() {
+    assert_eq!(Some(Angular), parse_preset("angular"));
+    assert_eq!(Some(ConventionalCommits), parse_preset("ConventionalCommits"));
+    assert_eq!(None, parse_preset("gitmoji"));
+}
+
+#[test]
+fn parse_bump_levels() {
+    assert_eq!(Some(Major), parse_bump("major"));
+    assert_eq!(Some(Minor), parse_bump("Minor"));
+    assert_eq!(Some(Patch), parse_bump("patch"));
+    assert_eq!(None, parse_bump("huge"));
+}
+
+#[test]
+fn release_as_footer() {
+    let commit = "0\nchore: Prepare release\n\nRelease-As: 1.5.0";
+    assert_eq!(Some(Version::parse("1.5.0").unwrap()), release_as(commit));
+}
+
+#[test]
+fn no_release_as_footer() {
+    let commit = "0\nfeat: This commit introduces a new feature";
+    assert_eq!(None, release_as(commit));
+}
+
+#[test]
+fn bumps_between_versions() {
+    let version = Version::parse("1.2.3").unwrap();
+    assert_eq!(Major, bump_between(&version, &Version::parse("2.0.0").unwrap()));
+    assert_eq!(Minor, bump_between(&version, &Version::parse("1.5.0").unwrap()));
+    assert_eq!(Patch, bump_between(&version, &Version::parse("1.2.7").unwrap()));
 }
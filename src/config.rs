@@ -1,4 +1,49 @@
 use git2::{Repository, Signature};
+use rustc_serialize::json::Json;
+use std::collections::BTreeMap;
+use std::time::Duration;
+use commit_analyzer::{CommitType, Preset, ZeroVersion};
+use webhook;
+use ci::Provider;
+use version_files::VersionReplacement;
+use sbom;
+
+fn string(value: &str) -> Json {
+    Json::String(value.to_owned())
+}
+
+fn optional(value: &Option<String>) -> Json {
+    value.as_ref().map(|v| string(v)).unwrap_or(Json::Null)
+}
+
+fn strings(values: &[String]) -> Json {
+    Json::Array(values.iter().map(|v| string(v)).collect())
+}
+
+/// Hides the value of a secret, only showing whether it is set.
+fn secret(value: &Option<String>) -> Json {
+    value.as_ref().map(|_| string("<set>")).unwrap_or(Json::Null)
+}
+
+/// Release steps that can be skipped with `--skip`.
+pub const STEPS: [&'static str; 6] = ["verify", "package", "push", "github", "cratesio", "notify"];
+
+/// Makes sure only known steps are skipped and that no remaining step
+/// depends on the outcome of a skipped one.
+pub fn check_skipped_steps(steps: &[String]) -> Result<(), String> {
+    for step in steps {
+        if !STEPS.contains(&&step[..]) {
+            return Err(format!("Unknown step `{}`, expected one of: {}", step, STEPS.join(", ")));
+        }
+    }
+
+    let skipped = |name: &str| steps.iter().any(|step| step == name);
+    if skipped("push") && !skipped("github") {
+        return Err("Skipping `push` requires skipping `github` too, GitHub needs the pushed tag".into());
+    }
+
+    Ok(())
+}
 
 pub struct Config {
     pub user: Option<String>,
@@ -11,13 +56,66 @@ pub struct Config {
     pub repository_path: String,
 
     pub write_mode: bool,
+    /// Why write mode is on or off, e.g. the flag or CI service that decided it.
+    pub write_mode_reason: String,
     pub release_mode: bool,
+    pub pull_request_mode: bool,
+
+    pub build_metadata: Option<String>,
+    pub ignore_prereleases: bool,
+    pub since: Option<String>,
+    pub github_prerelease: Option<bool>,
+    pub path_filter: Option<String>,
+    pub allow_dirty: bool,
+    pub strict_commits: bool,
+    pub pull_request_variables: Vec<(String, Option<String>)>,
+    pub also_bump: Vec<String>,
+    pub version_replacements: Vec<VersionReplacement>,
+    pub wait_for_index: Option<u64>,
+    pub fetch_tags: bool,
+    pub max_bump: Option<CommitType>,
+    pub skip: Vec<String>,
+    pub milestone: Option<String>,
+    pub run_hooks: bool,
+    pub registry: Option<String>,
+    pub package_args: Vec<String>,
+    pub publish_args: Vec<String>,
+    pub tag_wait_timeout: u64,
+    pub version_file: Option<String>,
+    pub preset: Preset,
+    pub zero_version: ZeroVersion,
+    pub squash_bodies: bool,
+    pub ignore_merges: bool,
+    pub version_heading: String,
+    pub date_utc_offset: i64,
+    pub ci_provider: Option<Provider>,
+    pub notes_from_changelog: Option<String>,
+    pub command_timeout: Option<u64>,
+    pub http_timeout: Option<u64>,
+    pub discussion_category: Option<String>,
+    pub skip_ci_token: Option<String>,
+    pub sbom_command: Option<String>,
+    pub sbom_file: Option<String>,
+    /// Whether `--branch` was given, which lets a detached HEAD be released as that branch.
+    pub explicit_branch: bool,
+    pub tag_message_template: Option<String>,
 
     pub repository: Repository,
     pub signature: Signature<'static>,
 
     pub gh_token: Option<String>,
     pub cargo_token: Option<String>,
+
+    pub ssh_key: Option<String>,
+    pub ssh_key_passphrase: Option<String>,
+
+    pub discord_webhook: Option<String>,
+    pub teams_webhook: Option<String>,
+
+    pub webhook_url: Option<String>,
+    pub webhook_method: String,
+    pub webhook_headers: Vec<(String, String)>,
+    pub webhook_body: String,
 }
 
 impl Config {
@@ -29,8 +127,142 @@ impl Config {
         self.can_push() && self.gh_token.is_some()
     }
 
+    /// Whether the version is kept in `Cargo.toml` rather than a plain version file.
+    pub fn is_cargo_project(&self) -> bool {
+        self.version_file.is_none()
+    }
+
     pub fn can_release_to_cratesio(&self) -> bool {
-        self.cargo_token.is_some()
+        self.is_cargo_project() && self.cargo_token.is_some()
+    }
+
+    /// The registry crates are published to, for display.
+    pub fn registry_name(&self) -> &str {
+        self.registry.as_ref().map(|r| &r[..]).unwrap_or("crates.io")
+    }
+
+    /// The resolved configuration, for debugging. Secrets only show whether they are set.
+    pub fn to_json(&self) -> Json {
+        let mut object = BTreeMap::new();
+        {
+            let mut set = |key: &str, value: Json| { object.insert(key.to_owned(), value); };
+
+            set("user", optional(&self.user));
+            set("repository_name", optional(&self.repository_name));
+            set("branch", string(&self.branch));
+            set("remote", match self.remote {
+                Ok(ref remote) => string(remote),
+                Err(_) => Json::Null,
+            });
+            set("repository_path", string(&self.repository_path));
+            set("write_mode", Json::Boolean(self.write_mode));
+            set("write_mode_reason", string(&self.write_mode_reason));
+            set("release_mode", Json::Boolean(self.release_mode));
+            set("pull_request_mode", Json::Boolean(self.pull_request_mode));
+            set("build_metadata", optional(&self.build_metadata));
+            set("ignore_prereleases", Json::Boolean(self.ignore_prereleases));
+            set("since", optional(&self.since));
+            set("github_prerelease", self.github_prerelease.map(Json::Boolean).unwrap_or(Json::Null));
+            set("path_filter", optional(&self.path_filter));
+            set("allow_dirty", Json::Boolean(self.allow_dirty));
+            set("strict_commits", Json::Boolean(self.strict_commits));
+            set("pull_request_variables", Json::Array(self.pull_request_variables.iter()
+                .map(|&(ref name, ref value)| match *value {
+                    Some(ref value) => string(&format!("{}={}", name, value)),
+                    None => string(name),
+                })
+                .collect()));
+            set("also_bump", strings(&self.also_bump));
+            set("version_replacements", Json::Array(self.version_replacements.iter().map(|replacement| {
+                let mut entry = BTreeMap::new();
+                entry.insert("file".to_owned(), string(&replacement.file));
+                entry.insert("pattern".to_owned(), string(&replacement.pattern));
+                entry.insert("replacement".to_owned(), optional(&replacement.replacement));
+                Json::Object(entry)
+            }).collect()));
+            set("wait_for_index", self.wait_for_index.map(Json::U64).unwrap_or(Json::Null));
+            set("fetch_tags", Json::Boolean(self.fetch_tags));
+            set("max_bump", self.max_bump.map(|bump| string(bump.as_str())).unwrap_or(Json::Null));
+            set("skip", strings(&self.skip));
+            set("milestone", optional(&self.milestone));
+            set("run_hooks", Json::Boolean(self.run_hooks));
+            set("registry", optional(&self.registry));
+            set("package_args", strings(&self.package_args));
+            set("publish_args", strings(&self.publish_args));
+            set("tag_wait_timeout", Json::U64(self.tag_wait_timeout));
+            set("version_file", optional(&self.version_file));
+            set("preset", string(self.preset.as_str()));
+            set("zero_version", string(self.zero_version.as_str()));
+            set("squash_bodies", Json::Boolean(self.squash_bodies));
+            set("ignore_merges", Json::Boolean(self.ignore_merges));
+            set("version_heading", string(&self.version_heading));
+            set("date_utc_offset", Json::I64(self.date_utc_offset));
+            set("notes_from_changelog", optional(&self.notes_from_changelog));
+            set("command_timeout", self.command_timeout.map(Json::U64).unwrap_or(Json::Null));
+            set("http_timeout", self.http_timeout.map(Json::U64).unwrap_or(Json::Null));
+            set("discussion_category", optional(&self.discussion_category));
+            set("skip_ci_token", optional(&self.skip_ci_token));
+            set("tag_message", optional(&self.tag_message_template));
+            set("sbom_command", optional(&self.sbom_command));
+            set("sbom_file", optional(&self.sbom_file));
+            set("ci", self.ci_provider.map(|provider| string(provider.name())).unwrap_or(Json::Null));
+            set("committer", string(&format!("{} <{}>",
+                self.signature.name().unwrap_or(""), self.signature.email().unwrap_or(""))));
+            set("gh_token", secret(&self.gh_token));
+            set("cargo_token", secret(&self.cargo_token));
+            set("ssh_key", optional(&self.ssh_key));
+            set("ssh_key_passphrase", secret(&self.ssh_key_passphrase));
+            set("discord_webhook", secret(&self.discord_webhook));
+            set("teams_webhook", secret(&self.teams_webhook));
+            set("webhook_url", secret(&self.webhook_url));
+            set("webhook_method", string(&self.webhook_method));
+            set("webhook_headers", Json::Array(self.webhook_headers.iter()
+                .map(|&(ref name, _)| string(&format!("{}: <set>", name)))
+                .collect()));
+            set("webhook_body", string(&self.webhook_body));
+        }
+        Json::Object(object)
+    }
+
+    /// The SBOM file generated for a version, if an SBOM is generated at all.
+    pub fn sbom_file_for(&self, version: &str) -> Option<String> {
+        match (&self.sbom_command, &self.sbom_file) {
+            (&Some(_), &Some(ref file)) => Some(sbom::render_file_name(file, version)),
+            _ => None,
+        }
+    }
+
+    /// How long cargo may run before it is stopped.
+    pub fn command_timeout(&self) -> Option<Duration> {
+        self.command_timeout.map(Duration::from_secs)
+    }
+
+    pub fn skips(&self, step: &str) -> bool {
+        self.skip.iter().any(|skipped| skipped == step)
+    }
+
+    pub fn can_notify_discord(&self) -> bool {
+        self.discord_webhook.is_some()
+    }
+
+    pub fn can_notify_teams(&self) -> bool {
+        self.teams_webhook.is_some()
+    }
+
+    pub fn can_notify_webhook(&self) -> bool {
+        self.webhook_url.is_some()
+    }
+
+    /// The branch the release commit is created on.
+    ///
+    /// In pull request mode this is a temporary branch named after the tag,
+    /// otherwise it's the release branch itself.
+    pub fn target_branch(&self, tag_name: &str) -> String {
+        if self.pull_request_mode {
+            format!("semantic-rs/release-{}", tag_name)
+        } else {
+            self.branch.clone()
+        }
     }
 }
 
@@ -45,13 +277,64 @@ pub struct ConfigBuilder {
     remote: Option<Result<String, String>>,
 
     write_mode: bool,
+    write_mode_reason: Option<String>,
     release_mode: bool,
+    pull_request_mode: bool,
+
+    build_metadata: Option<String>,
+    ignore_prereleases: bool,
+    since: Option<String>,
+    github_prerelease: Option<bool>,
+    path_filter: Option<String>,
+    allow_dirty: bool,
+    strict_commits: bool,
+    pull_request_variables: Vec<(String, Option<String>)>,
+    also_bump: Vec<String>,
+    version_replacements: Vec<VersionReplacement>,
+    wait_for_index: Option<u64>,
+    fetch_tags: bool,
+    max_bump: Option<CommitType>,
+    skip: Vec<String>,
+    milestone: Option<String>,
+    run_hooks: bool,
+    registry: Option<String>,
+    package_args: Vec<String>,
+    publish_args: Vec<String>,
+    tag_wait_timeout: u64,
+    version_file: Option<String>,
+    preset: Preset,
+    zero_version: ZeroVersion,
+    squash_bodies: bool,
+    ignore_merges: bool,
+    version_heading: Option<String>,
+    date_utc_offset: i64,
+    ci_provider: Option<Provider>,
+    notes_from_changelog: Option<String>,
+    command_timeout: Option<u64>,
+    http_timeout: Option<u64>,
+    discussion_category: Option<String>,
+    skip_ci_token: Option<String>,
+    sbom_command: Option<String>,
+    sbom_file: Option<String>,
+    explicit_branch: bool,
+    tag_message_template: Option<String>,
 
     repository: Option<Repository>,
     signature: Option<Signature<'static>>,
 
     gh_token: Option<String>,
     cargo_token: Option<String>,
+
+    ssh_key: Option<String>,
+    ssh_key_passphrase: Option<String>,
+
+    discord_webhook: Option<String>,
+    teams_webhook: Option<String>,
+
+    webhook_url: Option<String>,
+    webhook_method: Option<String>,
+    webhook_headers: Vec<(String, String)>,
+    webhook_body: Option<String>,
 }
 
 impl ConfigBuilder {
@@ -62,11 +345,58 @@ impl ConfigBuilder {
             branch: None,
             repository_path: None,
             write_mode: false,
+            write_mode_reason: None,
             release_mode: false,
+            pull_request_mode: false,
+            build_metadata: None,
+            ignore_prereleases: false,
+            since: None,
+            github_prerelease: None,
+            path_filter: None,
+            allow_dirty: false,
+            strict_commits: false,
+            pull_request_variables: vec![],
+            also_bump: vec![],
+            version_replacements: vec![],
+            wait_for_index: None,
+            fetch_tags: false,
+            max_bump: None,
+            skip: vec![],
+            milestone: None,
+            run_hooks: false,
+            registry: None,
+            package_args: vec![],
+            publish_args: vec![],
+            tag_wait_timeout: 30,
+            version_file: None,
+            preset: Preset::default(),
+            zero_version: ZeroVersion::default(),
+            squash_bodies: false,
+            ignore_merges: true,
+            version_heading: None,
+            date_utc_offset: 0,
+            ci_provider: None,
+            notes_from_changelog: None,
+            command_timeout: None,
+            http_timeout: None,
+            discussion_category: None,
+            skip_ci_token: None,
+            sbom_command: None,
+            sbom_file: None,
+            explicit_branch: false,
+            tag_message_template: None,
             repository: None,
             signature: None,
             gh_token: None,
             cargo_token: None,
+            ssh_key: None,
+            ssh_key_passphrase: None,
+            discord_webhook: None,
+            teams_webhook: None,
+            webhook_url: None,
+            webhook_method: None,
+            webhook_headers: vec![],
+            webhook_body: None,
             remote: None
         }
     }
@@ -101,11 +431,202 @@ impl ConfigBuilder {
         self
     }
 
+    pub fn write_mode_reason(&mut self, reason: String) -> &mut Self {
+        self.write_mode_reason = Some(reason);
+        self
+    }
+
     pub fn release(&mut self, mode: bool) -> &mut Self {
         self.release_mode = mode;
         self
     }
 
+    pub fn pull_request(&mut self, mode: bool) -> &mut Self {
+        self.pull_request_mode = mode;
+        self
+    }
+
+    pub fn build_metadata(&mut self, template: String) -> &mut Self {
+        self.build_metadata = Some(template);
+        self
+    }
+
+    pub fn ignore_prereleases(&mut self, ignore: bool) -> &mut Self {
+        self.ignore_prereleases = ignore;
+        self
+    }
+
+    pub fn since(&mut self, rev: String) -> &mut Self {
+        self.since = Some(rev);
+        self
+    }
+
+    pub fn github_prerelease(&mut self, prerelease: bool) -> &mut Self {
+        self.github_prerelease = Some(prerelease);
+        self
+    }
+
+    pub fn path_filter(&mut self, path: String) -> &mut Self {
+        self.path_filter = Some(path);
+        self
+    }
+
+    pub fn allow_dirty(&mut self, allow: bool) -> &mut Self {
+        self.allow_dirty = allow;
+        self
+    }
+
+    pub fn strict_commits(&mut self, strict: bool) -> &mut Self {
+        self.strict_commits = strict;
+        self
+    }
+
+    pub fn pull_request_variable(&mut self, name: String, value: Option<String>) -> &mut Self {
+        self.pull_request_variables.push((name, value));
+        self
+    }
+
+    pub fn version_replacement(&mut self, replacement: VersionReplacement) -> &mut Self {
+        self.version_replacements.push(replacement);
+        self
+    }
+
+    pub fn also_bump(&mut self, file: String) -> &mut Self {
+        self.also_bump.push(file);
+        self
+    }
+
+    pub fn wait_for_index(&mut self, seconds: u64) -> &mut Self {
+        self.wait_for_index = Some(seconds);
+        self
+    }
+
+    pub fn fetch_tags(&mut self, fetch: bool) -> &mut Self {
+        self.fetch_tags = fetch;
+        self
+    }
+
+    pub fn max_bump(&mut self, bump: CommitType) -> &mut Self {
+        self.max_bump = Some(bump);
+        self
+    }
+
+    pub fn skip(&mut self, step: String) -> &mut Self {
+        self.skip.push(step);
+        self
+    }
+
+    pub fn milestone(&mut self, template: String) -> &mut Self {
+        self.milestone = Some(template);
+        self
+    }
+
+    pub fn run_hooks(&mut self, run_hooks: bool) -> &mut Self {
+        self.run_hooks = run_hooks;
+        self
+    }
+
+    pub fn registry(&mut self, registry: String) -> &mut Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    pub fn package_arg(&mut self, arg: String) -> &mut Self {
+        self.package_args.push(arg);
+        self
+    }
+
+    pub fn publish_arg(&mut self, arg: String) -> &mut Self {
+        self.publish_args.push(arg);
+        self
+    }
+
+    pub fn tag_wait_timeout(&mut self, seconds: u64) -> &mut Self {
+        self.tag_wait_timeout = seconds;
+        self
+    }
+
+    pub fn version_file(&mut self, file: String) -> &mut Self {
+        self.version_file = Some(file);
+        self
+    }
+
+    pub fn preset(&mut self, preset: Preset) -> &mut Self {
+        self.preset = preset;
+        self
+    }
+
+    pub fn zero_version(&mut self, behavior: ZeroVersion) -> &mut Self {
+        self.zero_version = behavior;
+        self
+    }
+
+    pub fn squash_bodies(&mut self, squash_bodies: bool) -> &mut Self {
+        self.squash_bodies = squash_bodies;
+        self
+    }
+
+    pub fn ignore_merges(&mut self, ignore_merges: bool) -> &mut Self {
+        self.ignore_merges = ignore_merges;
+        self
+    }
+
+    pub fn version_heading(&mut self, template: String) -> &mut Self {
+        self.version_heading = Some(template);
+        self
+    }
+
+    pub fn date_utc_offset(&mut self, hours: i64) -> &mut Self {
+        self.date_utc_offset = hours;
+        self
+    }
+
+    pub fn ci_provider(&mut self, provider: Option<Provider>) -> &mut Self {
+        self.ci_provider = provider;
+        self
+    }
+
+    pub fn notes_from_changelog(&mut self, file: String) -> &mut Self {
+        self.notes_from_changelog = Some(file);
+        self
+    }
+
+    pub fn command_timeout(&mut self, seconds: u64) -> &mut Self {
+        self.command_timeout = Some(seconds);
+        self
+    }
+
+    pub fn http_timeout(&mut self, seconds: u64) -> &mut Self {
+        self.http_timeout = Some(seconds);
+        self
+    }
+
+    pub fn discussion_category(&mut self, category: String) -> &mut Self {
+        self.discussion_category = Some(category);
+        self
+    }
+
+    pub fn skip_ci_token(&mut self, token: String) -> &mut Self {
+        self.skip_ci_token = Some(token);
+        self
+    }
+
+    pub fn explicit_branch(&mut self, explicit: bool) -> &mut Self {
+        self.explicit_branch = explicit;
+        self
+    }
+
+    pub fn tag_message_template(&mut self, template: String) -> &mut Self {
+        self.tag_message_template = Some(template);
+        self
+    }
+
+    pub fn sbom(&mut self, command: String, file: String) -> &mut Self {
+        self.sbom_command = Some(command);
+        self.sbom_file = Some(file);
+        self
+    }
+
     pub fn signature(&mut self, sig: Signature<'static>) -> &mut Self {
         self.signature = Some(sig);
         self
@@ -121,6 +642,46 @@ impl ConfigBuilder {
         self
     }
 
+    pub fn ssh_key(&mut self, path: String) -> &mut Self {
+        self.ssh_key = Some(path);
+        self
+    }
+
+    pub fn ssh_key_passphrase(&mut self, passphrase: String) -> &mut Self {
+        self.ssh_key_passphrase = Some(passphrase);
+        self
+    }
+
+    pub fn discord_webhook(&mut self, url: String) -> &mut Self {
+        self.discord_webhook = Some(url);
+        self
+    }
+
+    pub fn teams_webhook(&mut self, url: String) -> &mut Self {
+        self.teams_webhook = Some(url);
+        self
+    }
+
+    pub fn webhook_url(&mut self, url: String) -> &mut Self {
+        self.webhook_url = Some(url);
+        self
+    }
+
+    pub fn webhook_method(&mut self, method: String) -> &mut Self {
+        self.webhook_method = Some(method);
+        self
+    }
+
+    pub fn webhook_header(&mut self, name: String, value: String) -> &mut Self {
+        self.webhook_headers.push((name, value));
+        self
+    }
+
+    pub fn webhook_body(&mut self, template: String) -> &mut Self {
+        self.webhook_body = Some(template);
+        self
+    }
+
     pub fn remote(&mut self, remote: Result<String, String>) -> &mut Self {
         self.remote = Some(remote);
         self
@@ -133,11 +694,58 @@ impl ConfigBuilder {
             branch: self.branch.unwrap_or("master".into()),
             repository_path: self.repository_path.unwrap(),
             write_mode: self.write_mode,
+            write_mode_reason: self.write_mode_reason.unwrap_or("default".into()),
             release_mode: self.release_mode,
+            pull_request_mode: self.pull_request_mode,
+            build_metadata: self.build_metadata,
+            ignore_prereleases: self.ignore_prereleases,
+            since: self.since,
+            github_prerelease: self.github_prerelease,
+            path_filter: self.path_filter,
+            allow_dirty: self.allow_dirty,
+            strict_commits: self.strict_commits,
+            pull_request_variables: self.pull_request_variables,
+            also_bump: self.also_bump,
+            version_replacements: self.version_replacements,
+            wait_for_index: self.wait_for_index,
+            fetch_tags: self.fetch_tags,
+            max_bump: self.max_bump,
+            skip: self.skip,
+            milestone: self.milestone,
+            run_hooks: self.run_hooks,
+            registry: self.registry,
+            package_args: self.package_args,
+            publish_args: self.publish_args,
+            tag_wait_timeout: self.tag_wait_timeout,
+            version_file: self.version_file,
+            preset: self.preset,
+            zero_version: self.zero_version,
+            squash_bodies: self.squash_bodies,
+            ignore_merges: self.ignore_merges,
+            version_heading: self.version_heading.unwrap_or("v{version} ({date})".into()),
+            date_utc_offset: self.date_utc_offset,
+            ci_provider: self.ci_provider,
+            notes_from_changelog: self.notes_from_changelog,
+            command_timeout: self.command_timeout,
+            http_timeout: self.http_timeout,
+            discussion_category: self.discussion_category,
+            skip_ci_token: self.skip_ci_token,
+            sbom_command: self.sbom_command,
+            sbom_file: self.sbom_file,
+            explicit_branch: self.explicit_branch,
+            tag_message_template: self.tag_message_template,
             repository: self.repository.unwrap(),
             signature: self.signature.unwrap(),
             gh_token: self.gh_token,
             cargo_token: self.cargo_token,
+            ssh_key: self.ssh_key,
+            ssh_key_passphrase: self.ssh_key_passphrase,
+            discord_webhook: self.discord_webhook,
+            teams_webhook: self.teams_webhook,
+            webhook_url: self.webhook_url,
+            webhook_method: self.webhook_method.unwrap_or("POST".into()),
+            webhook_headers: self.webhook_headers,
+            webhook_body: self.webhook_body.unwrap_or(webhook::DEFAULT_BODY.into()),
             remote: self.remote.unwrap_or(Err("No remote found".into()))
         }
     }
@@ -148,3 +756,35 @@ impl Default for ConfigBuilder {
         ConfigBuilder::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{check_skipped_steps, secret};
+    use rustc_serialize::json::Json;
+
+    fn steps(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn skipping_known_steps() {
+        assert!(check_skipped_steps(&steps(&["verify", "notify"])).is_ok());
+        assert!(check_skipped_steps(&steps(&["push", "github"])).is_ok());
+    }
+
+    #[test]
+    fn skipping_unknown_step() {
+        assert!(check_skipped_steps(&steps(&["verify_release"])).is_err());
+    }
+
+    #[test]
+    fn secrets_are_hidden() {
+        assert_eq!(Json::String("<set>".into()), secret(&Some("token".into())));
+        assert_eq!(Json::Null, secret(&None));
+    }
+
+    #[test]
+    fn skipping_push_requires_skipping_github() {
+        assert!(check_skipped_steps(&steps(&["push"])).is_err());
+    }
+}
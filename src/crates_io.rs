@@ -0,0 +1,39 @@
+use std::thread;
+use std::time::{Duration, Instant};
+use hyper::status::StatusCode;
+use error::Error;
+use http;
+
+const API_URL: &'static str = "https://crates.io/api/v1/crates";
+const POLL_INTERVAL_SECS: u64 = 5;
+
+/// Whether the given version of a crate is already published on crates.io.
+pub fn is_published(name: &str, version: &str) -> Result<bool, Error> {
+    let url = format!("{}/{}/{}", API_URL, name, version);
+    let response = try!(http::get(&url));
+
+    match response.status {
+        StatusCode::Ok => Ok(true),
+        StatusCode::NotFound => Ok(false),
+        status => Err(Error::HttpStatus(status)),
+    }
+}
+
+/// Polls crates.io until the version is available or the timeout has passed.
+///
+/// Returns whether the version became available in time.
+pub fn wait_until_published(name: &str, version: &str, timeout: Duration) -> Result<bool, Error> {
+    let start = Instant::now();
+
+    loop {
+        if try!(is_published(name, version)) {
+            return Ok(true);
+        }
+
+        if start.elapsed() >= timeout {
+            return Ok(false);
+        }
+
+        thread::sleep(Duration::from_secs(POLL_INTERVAL_SECS));
+    }
+}
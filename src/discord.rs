@@ -0,0 +1,25 @@
+use std::collections::BTreeMap;
+use rustc_serialize::json::Json;
+use error::Error;
+use http;
+use utils::truncate;
+
+// Discord rejects embeds with a longer description.
+const MAX_DESCRIPTION_LENGTH: usize = 4096;
+
+fn embed(title: &str, description: &str) -> Json {
+    let mut embed = BTreeMap::new();
+    embed.insert("title".to_owned(), Json::String(title.into()));
+    embed.insert("description".to_owned(), Json::String(truncate(description, MAX_DESCRIPTION_LENGTH)));
+
+    let mut body = BTreeMap::new();
+    body.insert("embeds".to_owned(), Json::Array(vec![Json::Object(embed)]));
+    Json::Object(body)
+}
+
+pub fn notify(webhook_url: &str, name: &str, tag_name: &str, release_notes: &str) -> Result<(), Error> {
+    let title = format!("{} {} is released", name, tag_name);
+    let body = embed(&title, release_notes).to_string();
+
+    http::post_json(webhook_url, &body)
+}
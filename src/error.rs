@@ -3,6 +3,9 @@ use std::env::VarError;
 use std::error::Error as StdError;
 use std::io::Error as IoError;
 use hubcaps::Error as HubcapsError;
+use hyper::Error as HyperError;
+use hyper::status::StatusCode;
+use rustc_serialize::json::ParserError;
 
 use std::fmt;
 use std::convert::From;
@@ -15,6 +18,11 @@ pub enum Error {
     Var(VarError),
     Io(IoError),
     GitHub(HubcapsError),
+    Http(HyperError),
+    HttpStatus(StatusCode),
+    Json(ParserError),
+    /// An error message returned by an API in an otherwise successful response.
+    Api(String),
 }
 
 impl From<GitError> for Error {
@@ -41,6 +49,18 @@ impl From<HubcapsError> for Error {
     }
 }
 
+impl From<HyperError> for Error {
+    fn from(err: HyperError) -> Error {
+        Error::Http(err)
+    }
+}
+
+impl From<ParserError> for Error {
+    fn from(err: ParserError) -> Error {
+        Error::Json(err)
+    }
+}
+
 impl fmt::Display for Error {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match *self {
@@ -48,6 +68,10 @@ impl fmt::Display for Error {
             Var(ref e) => e.fmt(f),
             Io(ref e) => e.fmt(f),
             GitHub(ref e) => e.fmt(f),
+            Http(ref e) => e.fmt(f),
+            HttpStatus(ref s) => write!(f, "Unexpected HTTP status: {}", s),
+            Json(ref e) => e.fmt(f),
+            Api(ref message) => write!(f, "API error: {}", message),
         }
 
     }
@@ -60,6 +84,23 @@ impl StdError for Error {
             Var(ref e) => e.description(),
             Io(ref e) => e.description(),
             GitHub(ref e) => e.description(),
+            Http(ref e) => e.description(),
+            HttpStatus(_) => "Unexpected HTTP status",
+            Json(ref e) => e.description(),
+            Api(_) => "API error",
+        }
+    }
+
+    fn cause(&self) -> Option<&StdError> {
+        match *self {
+            Git(ref e) => Some(e),
+            Var(ref e) => Some(e),
+            Io(ref e) => Some(e),
+            GitHub(ref e) => Some(e),
+            Http(ref e) => Some(e),
+            HttpStatus(_) => None,
+            Json(ref e) => Some(e),
+            Api(_) => None,
         }
     }
 }
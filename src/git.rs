@@ -1,32 +1,96 @@
 use std::path::Path;
+use std::io;
+use std::process::Command;
 use semver::Version;
 use std::env;
-use git2::{self, Repository, Commit, Signature, PushOptions, RemoteCallbacks, Cred};
+use git2::{self, Repository, Commit, Signature, PushOptions, FetchOptions, ProxyOptions, AutotagOption, RemoteCallbacks, Cred, DiffOptions, StatusOptions};
 
 use commit_analyzer::{self, CommitType};
 use error::Error;
 use config::Config;
 
-pub fn get_signature(repo: &Repository) -> Result<Signature, Error> {
-    let author = {
-        let mut author = env::var("GIT_COMMITTER_NAME").map_err(Error::from);
+/// The files a release commit is made of.
+const FILES_TO_COMMIT: [&'static str; 1] = ["Changelog.md"];
+
+/// The files the new version is written to, unless a version file is used instead.
+const CARGO_FILES: [&'static str; 2] = ["Cargo.toml", "Cargo.lock"];
+
+/// The identity used when GitHub Actions doesn't tell who triggered the run.
+const GITHUB_ACTIONS_BOT: (&'static str, &'static str) =
+    ("github-actions[bot]", "41898282+github-actions[bot]@users.noreply.github.com");
+
+/// Where the committer name and email address may come from.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum CommitterSource {
+    /// `GIT_COMMITTER_NAME` and `GIT_COMMITTER_EMAIL`
+    Env,
+    /// `user.name` and `user.email` from the git config
+    GitConfig,
+    /// The `GITHUB_ACTOR` of a GitHub Actions run, or the GitHub Actions bot
+    GitHub,
+    /// The identity given with `--default-committer`
+    Default,
+}
 
-        if author.is_err() {
-            let config = try!(repo.config());
-            author = config.get_string("user.name").map_err(Error::from);
-        }
-        try!(author)
-    };
+/// Parses a comma-separated list of committer sources, in order of precedence.
+pub fn parse_committer_sources(sources: &str) -> Result<Vec<CommitterSource>, String> {
+    sources.split(',')
+        .map(|source| match source.trim() {
+            "env" => Ok(CommitterSource::Env),
+            "git" => Ok(CommitterSource::GitConfig),
+            "github" => Ok(CommitterSource::GitHub),
+            "default" => Ok(CommitterSource::Default),
+            other => Err(format!("Unknown committer source `{}`, expected env, git, github or default", other)),
+        })
+        .collect()
+}
 
-    let email = {
-        let mut email = env::var("GIT_COMMITTER_EMAIL").map_err(Error::from);
+fn github_committer_with<F>(lookup: F) -> (Option<String>, Option<String>)
+    where F: Fn(&str) -> Option<String>
+{
+    if lookup("GITHUB_ACTIONS").is_none() {
+        return (None, None);
+    }
 
-        if email.is_err() {
-            let config = try!(repo.config());
-            email = config.get_string("user.email").map_err(Error::from);
-        }
-        try!(email)
-    };
+    match lookup("GITHUB_ACTOR") {
+        Some(actor) => {
+            let email = format!("{}@users.noreply.github.com", actor);
+            (Some(actor), Some(email))
+        },
+        None => (Some(GITHUB_ACTIONS_BOT.0.to_owned()), Some(GITHUB_ACTIONS_BOT.1.to_owned())),
+    }
+}
+
+/// The name and email address a single source provides, each if available.
+fn committer_from(source: CommitterSource, repo: &Repository, default: Option<&(String, String)>)
+    -> (Option<String>, Option<String>)
+{
+    match source {
+        CommitterSource::Env => (env::var("GIT_COMMITTER_NAME").ok(), env::var("GIT_COMMITTER_EMAIL").ok()),
+        CommitterSource::GitConfig => match repo.config() {
+            Ok(config) => (config.get_string("user.name").ok(), config.get_string("user.email").ok()),
+            Err(_) => (None, None),
+        },
+        CommitterSource::GitHub => github_committer_with(|name| env::var(name).ok()),
+        CommitterSource::Default => match default {
+            Some(&(ref name, ref email)) => (Some(name.clone()), Some(email.clone())),
+            None => (None, None),
+        },
+    }
+}
+
+/// The committer, with name and email address each taken from the first source providing it.
+pub fn get_signature(repo: &Repository, sources: &[CommitterSource], default: Option<&(String, String)>)
+    -> Result<Signature<'static>, Error>
+{
+    let identities = sources.iter()
+        .map(|&source| committer_from(source, repo, default))
+        .collect::<Vec<_>>();
+
+    let author = try!(identities.iter().filter_map(|i| i.0.clone()).next()
+        .ok_or(Error::from(env::VarError::NotPresent)));
+    let email = try!(identities.iter().filter_map(|i| i.1.clone()).next()
+        .ok_or(Error::from(env::VarError::NotPresent)));
 
     Signature::now(&author, &email).map_err(From::from)
 }
@@ -49,8 +113,8 @@ fn add<P: AsRef<Path>>(repo: &Repository, files: &[P]) -> Result<(), git2::Error
     index.write()
 }
 
-fn commit(config: &Config, message: &str) -> Result<(), git2::Error> {
-    let update_ref = format!("refs/heads/{}", config.branch);
+fn commit(config: &Config, branch: &str, message: &str) -> Result<(), git2::Error> {
+    let update_ref = format!("refs/heads/{}", branch);
     let repo = &config.repository;
 
     let oid = try!(repo.refname_to_id("HEAD"));
@@ -66,11 +130,66 @@ fn commit(config: &Config, message: &str) -> Result<(), git2::Error> {
         .map(|_| ())
 }
 
-fn create_tag(config: &Config, tag_name: &str, message: &str) -> Result<(), git2::Error> {
+/// A `git` command run in the repository as the configured committer.
+///
+/// libgit2 never runs hooks, so this is used instead when hooks are wanted.
+fn git_command(config: &Config) -> Command {
+    let name  = config.signature.name().unwrap_or("");
+    let email = config.signature.email().unwrap_or("");
+
+    let mut command = Command::new("git");
+    command.current_dir(&config.repository_path)
+        .env("GIT_AUTHOR_NAME", name)
+        .env("GIT_AUTHOR_EMAIL", email)
+        .env("GIT_COMMITTER_NAME", name)
+        .env("GIT_COMMITTER_EMAIL", email);
+    command
+}
+
+fn run_git(config: &Config, args: &[&str]) -> Result<(), Error> {
+    let status = try!(git_command(config).args(args).status());
+
+    if status.success() {
+        Ok(())
+    } else {
+        let message = format!("`git {}` failed", args.join(" "));
+        Err(Error::from(io::Error::new(io::ErrorKind::Other, message)))
+    }
+}
+
+/// Commits the staged files with the `git` CLI, so that commit hooks run.
+///
+/// Like the libgit2 commit, this has to move `refs/heads/<branch>`, which a plain `git commit`
+/// doesn't do on a detached HEAD. Checking the branch out first carries the staged changes over,
+/// but leaves it checked out afterwards, see `checkout`.
+fn commit_with_hooks(config: &Config, branch: &str, message: &str) -> Result<(), Error> {
+    try!(run_git(config, &["checkout", "-B", branch]));
+    run_git(config, &["commit", "-m", message])
+}
+
+/// What HEAD points to: the checked out branch, or the commit if it is detached.
+pub fn head_name(repo: &Repository) -> Option<String> {
+    let head = match repo.head() {
+        Ok(head) => head,
+        Err(_) => return None
+    };
+
+    if head.is_branch() {
+        head.shorthand().map(|name| name.to_owned())
+    } else {
+        head.target().map(|oid| oid.to_string())
+    }
+}
+
+/// Checks out a branch or commit with the `git` CLI.
+pub fn checkout(config: &Config, rev: &str) -> Result<(), Error> {
+    run_git(config, &["checkout", "--quiet", rev])
+}
+
+fn create_tag(config: &Config, rev: &str, tag_name: &str, message: &str) -> Result<(), git2::Error> {
     let repo = &config.repository;
 
-    let rev = format!("refs/heads/{}", config.branch);
-    let obj = try!(repo.revparse_single(&rev));
+    let obj = try!(repo.revparse_single(rev));
 
     repo.tag(tag_name, &obj, &config.signature, message, false)
         .map(|_| ())
@@ -84,7 +203,16 @@ fn is_https_remote(maybe_remote: Option<&str>) -> bool {
     }
 }
 
-pub fn latest_tag(repo: &Repository) -> Option<Version> {
+pub fn short_head_sha(repo: &Repository) -> Option<String> {
+    let head = match repo.head() {
+        Ok(head) => head,
+        Err(_) => return None
+    };
+
+    head.target().map(|oid| oid.to_string()[..7].to_owned())
+}
+
+pub fn latest_tag(repo: &Repository, ignore_prereleases: bool) -> Option<Version> {
     let tags = match repo.tag_names(None) {
         Ok(tags) => tags,
         Err(_) => return None
@@ -93,82 +221,441 @@ pub fn latest_tag(repo: &Repository) -> Option<Version> {
     tags.iter()
         .map(|tag| tag.unwrap())
         .filter_map(|tag| Version::parse(&tag[1..]).ok())
+        .filter(|version| !(ignore_prereleases && version.is_prerelease()))
         .max()
 }
 
-pub fn version_bump_since_latest(repo: &Repository) -> CommitType {
-    match latest_tag(repo) {
-        Some(t) => {
-            let tag = format!("v{}", t.to_string());
-            version_bump_since_tag(repo, &tag)
-        },
-        None => CommitType::Major
+/// The message of an annotated tag, or `None` for a lightweight one.
+pub fn tag_message(repo: &Repository, tag_name: &str) -> Result<Option<String>, Error> {
+    let obj = try!(repo.revparse_single(&format!("refs/tags/{}", tag_name)));
+    Ok(obj.as_tag().and_then(|tag| tag.message()).map(|message| message.to_owned()))
+}
+
+/// The revision the analysis starts from: the configured one or the latest release tag.
+pub fn start_revision(config: &Config) -> Option<String> {
+    if let Some(ref since) = config.since {
+        return Some(since.clone());
     }
+
+    latest_tag(&config.repository, config.ignore_prereleases)
+        .map(|t| format!("v{}", t.to_string()))
 }
 
-pub fn version_bump_since_tag(repo: &Repository, tag: &str) -> CommitType {
-    let tag = range_to_head(tag);
+/// Whether the commit changed anything below `path`, compared to its first parent.
+fn touches_path(repo: &Repository, commit: &Commit, path: &str) -> bool {
+    let tree = commit.tree().expect("No tree found for commit");
+    let parent_tree = commit.parent(0).ok()
+        .map(|parent| parent.tree().expect("No tree found for commit"));
+
+    let mut opts = DiffOptions::new();
+    opts.pathspec(path);
+
+    let diff = repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut opts))
+        .expect("Diffing commit failed");
+    diff.deltas().len() > 0
+}
+
+/// Whether the last release can't be determined because the history is incomplete.
+///
+/// In a shallow clone without any release tag we can't tell a first release
+/// apart from tags and commits that simply weren't fetched.
+pub fn is_history_incomplete(config: &Config) -> bool {
+    config.repository.is_shallow() && start_revision(config).is_none()
+}
+
+/// All commits after `rev` up to HEAD, newest first. Without `rev` the whole history is used.
+///
+/// If a path filter is configured, only commits touching that path are returned.
+/// Merge commits are left out unless configured otherwise, the commits they merge are walked anyway.
+fn commits_since(config: &Config, rev: Option<&str>) -> Vec<String> {
+    let repo = &config.repository;
 
     let mut walker = repo.revwalk().expect("Creating a revwalk failed");
-    walker.push_range(&tag).expect("Adding a range failed");
+    match rev {
+        Some(rev) => walker.push_range(&range_to_head(rev)).expect("Adding a range failed"),
+        None => walker.push_head().expect("Adding HEAD failed"),
+    }
 
     walker.map(|c| repo.find_commit(c.expect("Not a valid commit")).expect("No commit found"))
+        .filter(|c| !config.ignore_merges || c.parent_ids().count() <= 1)
+        .filter(|c| match config.path_filter {
+            Some(ref path) => touches_path(repo, c, path),
+            None => true
+        })
         .map(format_commit)
-        .map(|c| commit_analyzer::analyze_single(&c).expect("Analyzing commit failed"))
+        .collect()
+}
+
+/// The commits since the last release whose subject doesn't follow the preset, as `<sha> <subject>`.
+pub fn nonconforming_commits(config: &Config) -> Vec<String> {
+    let start = start_revision(config);
+    commits_since(config, start.as_ref().map(|s| &s[..])).iter()
+        .filter_map(|c| {
+            let mut lines = c.lines();
+            let id = lines.next().unwrap_or("");
+            let subject = lines.next().unwrap_or("");
+            if commit_analyzer::is_conforming_subject(subject, config.preset) {
+                None
+            } else {
+                Some(format!("{} {}", &id[..::std::cmp::min(7, id.len())], subject))
+            }
+        })
+        .collect()
+}
+
+pub fn version_bump_since_latest(config: &Config) -> CommitType {
+    match start_revision(config) {
+        Some(tag) => version_bump_since_tag(config, &tag),
+        None => CommitType::Major
+    }
+}
+
+pub fn version_bump_since_tag(config: &Config, tag: &str) -> CommitType {
+    commits_since(config, Some(tag)).iter()
+        .map(|c| if config.squash_bodies {
+            commit_analyzer::analyze_squashed(c, config.preset)
+        } else {
+            commit_analyzer::analyze_single(c, config.preset)
+        })
+        .map(|bump| bump.expect("Analyzing commit failed"))
         .max().unwrap_or(CommitType::Unknown)
 }
 
-pub fn generate_commit_message(new_version: &str) -> String {
-    format!("Bump version to {}", new_version).into()
+/// The version requested by the most recent `Release-As` footer since the last release, if any.
+pub fn release_as_since_latest(config: &Config) -> Option<Version> {
+    let start = start_revision(config);
+    commits_since(config, start.as_ref().map(|s| &s[..])).iter()
+        .filter_map(|c| commit_analyzer::release_as(c))
+        .next()
 }
 
-pub fn commit_files(config: &Config, new_version: &str) -> Result<(), Error> {
+pub fn generate_commit_message(new_version: &str, skip_ci_token: Option<&str>) -> String {
+    match skip_ci_token {
+        Some(token) => format!("Bump version to {} {}", new_version, token),
+        None => format!("Bump version to {}", new_version),
+    }
+}
+
+/// The default release files plus the configured additional ones.
+///
+/// The Cargo files are only part of it if the version is written there.
+/// The generated SBOM is only known once the new version is.
+fn files_to_commit(config: &Config, new_version: Option<&str>) -> Vec<String> {
+    let cargo_files: &[&str] = if config.is_cargo_project() { &CARGO_FILES } else { &[] };
+
+    FILES_TO_COMMIT.iter()
+        .chain(cargo_files)
+        .map(|file| file.to_string())
+        .chain(config.version_file.iter().cloned())
+        .chain(config.also_bump.iter().cloned())
+        .chain(config.version_replacements.iter().map(|replacement| replacement.file.clone()))
+        .chain(new_version.and_then(|version| config.sbom_file_for(version)))
+        .collect()
+}
+
+pub fn commit_files(config: &Config, branch: &str, new_version: &str) -> Result<(), Error> {
     let repo = &config.repository;
-    let files = ["Cargo.toml", "Cargo.lock", "Changelog.md"];
+    let files = files_to_commit(config, Some(new_version));
     let files = files.iter().filter(|filename| {
         let path = Path::new(filename);
-        !repo.status_should_ignore(path).expect("Determining ignore status of file failed")
+        Path::new(&config.repository_path).join(path).exists() &&
+            !repo.status_should_ignore(path).expect("Determining ignore status of file failed")
     }).collect::<Vec<_>>();
 
     try!(add(&config.repository, &files[..]));
 
-    commit(config, &generate_commit_message(new_version)).map_err(Error::from)
+    let message = generate_commit_message(new_version, config.skip_ci_token.as_ref().map(|t| &t[..]));
+    if config.run_hooks {
+        commit_with_hooks(config, branch, &message)
+    } else {
+        commit(config, branch, &message).map_err(Error::from)
+    }
+}
+
+/// Lists all modified, staged or untracked files that would not be part of the release commit.
+pub fn unexpected_changes(config: &Config) -> Result<Vec<String>, Error> {
+    let mut opts = StatusOptions::new();
+    opts.include_untracked(true)
+        .include_ignored(false);
+
+    let release_files = files_to_commit(config, None);
+    let statuses = try!(config.repository.statuses(Some(&mut opts)));
+    let files = statuses.iter()
+        .filter(|entry| entry.status() != git2::Status::CURRENT)
+        .filter_map(|entry| entry.path().map(|p| p.to_owned()))
+        .filter(|path| !release_files.contains(path))
+        .collect();
+
+    Ok(files)
 }
 
-pub fn tag(config: &Config, tag_name: &str, tag_message: &str) -> Result<(), Error> {
-    create_tag(config, &tag_name, &tag_message)
+/// Fills in `{version}` and `{tag}` in a tag message template.
+pub fn render_tag_message(template: &str, version: &str, tag_name: &str) -> String {
+    template.replace("{version}", version).replace("{tag}", tag_name)
+}
+
+pub fn tag(config: &Config, branch: &str, tag_name: &str, tag_message: &str) -> Result<(), Error> {
+    create_tag(config, &format!("refs/heads/{}", branch), &tag_name, &tag_message)
         .map_err(Error::from)
 }
 
-pub fn push(config: &Config, tag_name: &str) -> Result<(), Error> {
-    let repo      = &config.repository;
+/// Tags the checked out commit, e.g. the merge commit of a release pull request.
+pub fn tag_head(config: &Config, tag_name: &str, tag_message: &str) -> Result<(), Error> {
+    create_tag(config, "HEAD", &tag_name, &tag_message)
+        .map_err(Error::from)
+}
 
-    let branch    = &config.branch;
-    let token     = config.gh_token.as_ref();
+pub fn tag_exists(repo: &Repository, tag_name: &str) -> bool {
+    repo.revparse_single(&format!("refs/tags/{}", tag_name)).is_ok()
+}
 
-    // We need to push both the branch we just committed as well as the tag we created.
-    let branch_ref = format!("refs/heads/{}", branch);
-    let tag_ref    = format!("refs/tags/{}", tag_name);
-    let refs = [&branch_ref[..], &tag_ref[..]];
+/// Authentication for the origin remote: the GitHub token for HTTPS remotes, ssh otherwise.
+fn remote_callbacks<'a>(config: &'a Config, https: bool) -> RemoteCallbacks<'a> {
+    let token      = config.gh_token.as_ref();
+    let ssh_key    = config.ssh_key.as_ref();
+    let passphrase = config.ssh_key_passphrase.as_ref();
 
-    let mut remote = try!(repo.find_remote("origin"));
     let mut cbs = RemoteCallbacks::new();
-    let mut opts = PushOptions::new();
 
-    if is_https_remote(remote.url()) {
-        cbs.credentials(|_url, _username, _allowed| {
-            Cred::userpass_plaintext(&token.unwrap(), "")
+    if https {
+        cbs.credentials(move |_url, _username, _allowed| {
+            match token {
+                Some(token) => Cred::userpass_plaintext(token, ""),
+                None => Err(git2::Error::from_str("No GH_TOKEN configured for the HTTPS remote")),
+            }
         });
-        opts.remote_callbacks(cbs);
     } else {
-        cbs.credentials(|_url, username, _allowed| {
-            Cred::ssh_key_from_agent(&username.unwrap())
+        // An explicitly configured key takes precedence over the ssh agent,
+        // e.g. for deploy keys on CI machines without an agent.
+        cbs.credentials(move |_url, username, _allowed| {
+            match ssh_key {
+                Some(key) => Cred::ssh_key(&username.unwrap(), None, Path::new(key), passphrase.map(|p| &p[..])),
+                None => Cred::ssh_key_from_agent(&username.unwrap()),
+            }
         });
-        opts.remote_callbacks(cbs);
     }
 
+    cbs
+}
+
+/// Proxy settings detected the way git does, from `http.proxy` and the proxy variables.
+fn proxy_options<'a>() -> ProxyOptions<'a> {
+    let mut proxy = ProxyOptions::new();
+    proxy.auto();
+    proxy
+}
+
+/// Fetches all tags from the origin remote, so the last release can be found.
+pub fn fetch_tags(config: &Config) -> Result<(), Error> {
+    let mut remote = try!(config.repository.find_remote("origin"));
+    let https = is_https_remote(remote.url());
+
+    let mut opts = FetchOptions::new();
+    opts.remote_callbacks(remote_callbacks(config, https))
+        .proxy_options(proxy_options())
+        .download_tags(AutotagOption::All);
+
+    remote
+        .fetch(&["refs/tags/*:refs/tags/*"], Some(&mut opts), None)
+        .map_err(Error::from)
+}
+
+/// Pushes the release branch and, unless the release is tagged later, the tag.
+pub fn push(config: &Config, branch: &str, tag_name: Option<&str>) -> Result<(), Error> {
+    let mut refs = vec![format!("refs/heads/{}", branch)];
+    refs.extend(tag_name.map(|tag_name| format!("refs/tags/{}", tag_name)));
+    push_refs(config, &refs)
+}
+
+pub fn push_tag(config: &Config, tag_name: &str) -> Result<(), Error> {
+    push_refs(config, &[format!("refs/tags/{}", tag_name)])
+}
+
+fn push_refs(config: &Config, refs: &[String]) -> Result<(), Error> {
+    let repo = &config.repository;
+    let refs = refs.iter().map(|r| &r[..]).collect::<Vec<_>>();
+
+    if config.run_hooks {
+        // The CLI uses git's own credential setup instead of the token or ssh key.
+        let mut args = vec!["push", "origin"];
+        args.extend(refs.iter().cloned());
+        return run_git(config, &args);
+    }
+
+    let mut remote = try!(repo.find_remote("origin"));
+    let https = is_https_remote(remote.url());
+
+    let mut opts = PushOptions::new();
+    opts.remote_callbacks(remote_callbacks(config, https))
+        .proxy_options(proxy_options());
+
     remote
         .push(&refs, Some(&mut opts))
         .map(|_| ())
         .map_err(Error::from)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use config::ConfigBuilder;
+
+    fn lookup(vars: &[(&str, &str)], name: &str) -> Option<String> {
+        vars.iter()
+            .find(|&&(var, _)| var == name)
+            .map(|&(_, value)| value.to_owned())
+    }
+
+    fn commit_on(repo: &Repository, update_ref: Option<&str>, message: &str, parents: &[&Commit]) -> git2::Oid {
+        let signature = Signature::now("Test", "test@example.com").unwrap();
+        let tree_id = repo.index().unwrap().write_tree().unwrap();
+        let tree = repo.find_tree(tree_id).unwrap();
+        repo.commit(update_ref, &signature, &signature, message, &tree, parents).unwrap()
+    }
+
+    fn config_with_merge(path: &Path, ignore_merges: bool) -> Config {
+        let repo = Repository::init(path).unwrap();
+        {
+            let root = repo.find_commit(commit_on(&repo, None, "chore: Initial commit", &[])).unwrap();
+            let feature = repo.find_commit(commit_on(&repo, None, "feat: Add a feature", &[&root])).unwrap();
+            let fix = repo.find_commit(commit_on(&repo, None, "fix: Fix a bug", &[&root])).unwrap();
+            commit_on(&repo, Some("HEAD"), "feat: Merge branch 'feature'", &[&fix, &feature]);
+        }
+
+        let mut builder = ConfigBuilder::new();
+        builder.repository_path(path.to_str().unwrap().to_owned());
+        builder.signature(Signature::now("Test", "test@example.com").unwrap());
+        builder.ignore_merges(ignore_merges);
+        builder.repository(repo);
+        builder.build()
+    }
+
+    #[test]
+    fn skips_merge_commits() {
+        let path = env::temp_dir().join(format!("semantic-rs-merges-{}", ::std::process::id()));
+        let _ = ::std::fs::remove_dir_all(&path);
+
+        let subjects = |config: &Config| commits_since(config, None).iter()
+            .map(|c| c.lines().nth(1).unwrap_or("").to_owned())
+            .collect::<Vec<_>>();
+
+        let ignoring = config_with_merge(&path.join("ignoring"), true);
+        let commits = subjects(&ignoring);
+        assert_eq!(3, commits.len());
+        assert!(!commits.contains(&"feat: Merge branch 'feature'".to_owned()));
+
+        let including = config_with_merge(&path.join("including"), false);
+        assert_eq!(4, subjects(&including).len());
+
+        let _ = ::std::fs::remove_dir_all(&path);
+    }
+
+    /// Writes and stages a file, so the next commit adds it.
+    fn stage_file(repo: &Repository, path: &Path, file: &str) {
+        let full_path = path.join(file);
+        ::std::fs::create_dir_all(full_path.parent().unwrap()).unwrap();
+        ::std::fs::write(&full_path, file).unwrap();
+
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new(file)).unwrap();
+        index.write().unwrap();
+    }
+
+    #[test]
+    fn filters_commits_by_path() {
+        let path = env::temp_dir().join(format!("semantic-rs-paths-{}", ::std::process::id()));
+        let _ = ::std::fs::remove_dir_all(&path);
+
+        let repo = Repository::init(&path).unwrap();
+        {
+            stage_file(&repo, &path, "crates/core/lib.rs");
+            let inside = repo.find_commit(commit_on(&repo, Some("HEAD"), "feat: Add core", &[])).unwrap();
+            stage_file(&repo, &path, "docs/index.md");
+            commit_on(&repo, Some("HEAD"), "docs: Add docs", &[&inside]);
+        }
+
+        let mut builder = ConfigBuilder::new();
+        builder.repository_path(path.to_str().unwrap().to_owned());
+        builder.signature(Signature::now("Test", "test@example.com").unwrap());
+        builder.path_filter("crates/core".to_owned());
+        builder.repository(repo);
+        let config = builder.build();
+
+        let commits = commits_since(&config, None);
+        assert_eq!(1, commits.len());
+        assert_eq!(Some("feat: Add core"), commits[0].lines().nth(1));
+
+        let _ = ::std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn cargo_files_are_unexpected_with_version_file() {
+        let path = env::temp_dir().join(format!("semantic-rs-unexpected-{}", ::std::process::id()));
+        let _ = ::std::fs::remove_dir_all(&path);
+
+        let config_with = |version_file: Option<&str>| {
+            let mut builder = ConfigBuilder::new();
+            builder.repository_path(path.to_str().unwrap().to_owned());
+            builder.signature(Signature::now("Test", "test@example.com").unwrap());
+            if let Some(file) = version_file {
+                builder.version_file(file.to_owned());
+            }
+            builder.repository(Repository::open(&path).unwrap());
+            builder.build()
+        };
+
+        let repo = Repository::init(&path).unwrap();
+        stage_file(&repo, &path, "Cargo.toml");
+        stage_file(&repo, &path, "VERSION");
+
+        assert!(unexpected_changes(&config_with(None)).unwrap().contains(&"VERSION".to_owned()));
+        assert!(!unexpected_changes(&config_with(None)).unwrap().contains(&"Cargo.toml".to_owned()));
+        assert_eq!(vec!["Cargo.toml".to_owned()], unexpected_changes(&config_with(Some("VERSION"))).unwrap());
+
+        let _ = ::std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn latest_tag_skips_prereleases() {
+        let path = env::temp_dir().join(format!("semantic-rs-prereleases-{}", ::std::process::id()));
+        let _ = ::std::fs::remove_dir_all(&path);
+
+        let repo = Repository::init(&path).unwrap();
+        {
+            let release = repo.find_commit(commit_on(&repo, Some("HEAD"), "feat: Add a feature", &[])).unwrap();
+            repo.tag_lightweight("v1.1.0", release.as_object(), false).unwrap();
+            let rc = repo.find_commit(commit_on(&repo, Some("HEAD"), "feat: Add another feature", &[&release])).unwrap();
+            repo.tag_lightweight("v1.2.0-rc.1", rc.as_object(), false).unwrap();
+        }
+
+        assert_eq!(Some(Version::parse("1.1.0").unwrap()), latest_tag(&repo, true));
+        assert_eq!(Some(Version::parse("1.2.0-rc.1").unwrap()), latest_tag(&repo, false));
+
+        let _ = ::std::fs::remove_dir_all(&path);
+    }
+
+    #[test]
+    fn renders_tag_message() {
+        assert_eq!("Release 1.2.0 (v1.2.0)", render_tag_message("Release {version} ({tag})", "1.2.0", "v1.2.0"));
+    }
+
+    #[test]
+    fn parses_committer_sources() {
+        assert_eq!(Ok(vec![CommitterSource::GitHub, CommitterSource::Env]), parse_committer_sources("github, env"));
+        assert!(parse_committer_sources("env,svn").is_err());
+    }
+
+    #[test]
+    fn github_actor_as_committer() {
+        let vars = [("GITHUB_ACTIONS", "true"), ("GITHUB_ACTOR", "octocat")];
+        assert_eq!((Some("octocat".to_owned()), Some("octocat@users.noreply.github.com".to_owned())),
+                   github_committer_with(|name| lookup(&vars, name)));
+    }
+
+    #[test]
+    fn github_actions_bot_as_committer() {
+        let (name, _) = github_committer_with(|name| lookup(&[("GITHUB_ACTIONS", "true")], name));
+        assert_eq!(Some("github-actions[bot]".to_owned()), name);
+        assert_eq!((None, None), github_committer_with(|name| lookup(&[], name)));
+    }
+}
@@ -1,11 +1,20 @@
-use hyper::Client;
-use hyper::net::HttpsConnector;
-use hyper_native_tls::NativeTlsClient;
 use hubcaps::{Github, Credentials};
+use semver::Version;
 use hubcaps::releases::ReleaseOptions;
+use hubcaps::pulls::PullOptions;
+use hyper::method::Method;
+use hyper::status::StatusCode;
+use std::collections::BTreeMap;
+use std::thread;
+use std::time::{Duration, Instant};
+use rustc_serialize::json::Json;
 use error::Error;
 use super::USERAGENT;
 use config::Config;
+use http;
+
+const API_URL: &'static str = "https://api.github.com";
+const POLL_INTERVAL_SECS: u64 = 1;
 
 pub fn can_release(config: &Config) -> bool {
     let repo = &config.repository;
@@ -25,26 +34,37 @@ pub fn is_github_url(url: &str) -> bool {
     url.contains("github.com")
 }
 
-pub fn release(config: &Config, tag_name: &str, tag_message: &str) -> Result<(), Error> {
+fn client(config: &Config) -> Github {
+    let token = config.gh_token.as_ref().unwrap();
+
+    let credentials = Credentials::Token(token.to_owned());
+    Github::new(USERAGENT, http::client(API_URL), credentials)
+}
+
+/// Whether the release should be marked as a prerelease on GitHub.
+///
+/// Unless explicitly configured, this follows the prerelease part of the version.
+fn is_prerelease(config: &Config, version: &str) -> bool {
+    match config.github_prerelease {
+        Some(prerelease) => prerelease,
+        None => Version::parse(version).map(|v| v.is_prerelease()).unwrap_or(false)
+    }
+}
+
+pub fn release(config: &Config, version: &str, tag_name: &str, tag_message: &str) -> Result<(), Error> {
     let user      = &config.user.as_ref().unwrap()[..];
     let repo_name = &config.repository_name.as_ref().unwrap()[..];
     let branch    = &config.branch[..];
-    let token     = config.gh_token.as_ref().unwrap();
+    let prerelease = is_prerelease(config, version);
 
-    let client = Client::with_connector(
-        HttpsConnector::new(
-            NativeTlsClient::new().unwrap()
-        )
-    );
-    let credentials = Credentials::Token(token.to_owned());
-    let github = Github::new(USERAGENT, client, credentials);
+    let github = client(config);
 
     let opts = ReleaseOptions::builder(tag_name)
         .name(tag_name)
         .body(tag_message)
         .commitish(branch)
         .draft(false)
-        .prerelease(false)
+        .prerelease(prerelease)
         .build();
 
     let repo = github.repo(user, repo_name);
@@ -55,3 +75,226 @@ pub fn release(config: &Config, tag_name: &str, tag_message: &str) -> Result<(),
         .map(|_| ())
         .map_err(Error::from)
 }
+
+pub fn open_pull_request(config: &Config, head: &str, tag_name: &str, body: &str) -> Result<(), Error> {
+    let user      = &config.user.as_ref().unwrap()[..];
+    let repo_name = &config.repository_name.as_ref().unwrap()[..];
+    let base      = &config.branch[..];
+
+    let github = client(config);
+
+    let title = format!("Release {}", tag_name);
+    let opts = PullOptions::new(&title[..], head, base, Some(body));
+
+    let repo = github.repo(user, repo_name);
+    let pulls = repo.pulls();
+
+    pulls
+        .create(&opts)
+        .map(|_| ())
+        .map_err(Error::from)
+}
+
+fn auth_headers(config: &Config) -> Vec<(String, String)> {
+    let token = config.gh_token.as_ref().unwrap();
+    vec![("Authorization".to_string(), format!("token {}", token))]
+}
+
+/// Whether GitHub already knows about the given tag.
+pub fn tag_exists(config: &Config, tag_name: &str) -> Result<bool, Error> {
+    let user      = &config.user.as_ref().unwrap()[..];
+    let repo_name = &config.repository_name.as_ref().unwrap()[..];
+
+    // The plural `git/refs` endpoint matches prefixes, e.g. `v1.0.0-rc.1` for `v1.0.0`.
+    let url = format!("{}/repos/{}/{}/git/ref/tags/{}", API_URL, user, repo_name, tag_name);
+    match try!(http::status(&url, &auth_headers(config))) {
+        StatusCode::Ok => Ok(true),
+        StatusCode::NotFound => Ok(false),
+        status => Err(Error::HttpStatus(status)),
+    }
+}
+
+/// Polls GitHub until a pushed tag is visible or the timeout has passed.
+///
+/// Returns whether the tag became visible in time.
+pub fn wait_for_tag(config: &Config, tag_name: &str, timeout: Duration) -> Result<bool, Error> {
+    let start = Instant::now();
+
+    loop {
+        if try!(tag_exists(config, tag_name)) {
+            return Ok(true);
+        }
+
+        if start.elapsed() >= timeout {
+            return Ok(false);
+        }
+
+        thread::sleep(Duration::from_secs(POLL_INTERVAL_SECS));
+    }
+}
+
+/// Runs a GraphQL query, returning its `data` or the error messages GitHub reported.
+fn graphql(config: &Config, query: &str, variables: BTreeMap<String, Json>) -> Result<Json, Error> {
+    let mut request = BTreeMap::new();
+    request.insert("query".to_owned(), Json::String(query.to_owned()));
+    request.insert("variables".to_owned(), Json::Object(variables));
+
+    let url = format!("{}/graphql", API_URL);
+    let body = try!(http::send_json_for_body(Method::Post, &url, &auth_headers(config), &Json::Object(request).to_string()));
+    let response = try!(Json::from_str(&body));
+
+    if let Some(errors) = response.find("errors").and_then(|e| e.as_array()) {
+        let messages = errors.iter()
+            .filter_map(|e| e.find("message").and_then(|m| m.as_string()))
+            .collect::<Vec<_>>();
+        return Err(Error::Api(messages.join(", ")));
+    }
+
+    Ok(response.find("data").cloned().unwrap_or(Json::Null))
+}
+
+const DISCUSSION_CATEGORIES_QUERY: &'static str = "query($owner: String!, $name: String!) { \
+    repository(owner: $owner, name: $name) { \
+        id hasDiscussionsEnabled discussionCategories(first: 100) { nodes { id name } } } }";
+
+const CREATE_DISCUSSION_MUTATION: &'static str = "mutation($repositoryId: ID!, $categoryId: ID!, $title: String!, $body: String!) { \
+    createDiscussion(input: {repositoryId: $repositoryId, categoryId: $categoryId, title: $title, body: $body}) { \
+        discussion { url } } }";
+
+/// Finds the id of the discussion category with the given name in a repository query result.
+fn find_category(repository: &Json, name: &str) -> Option<String> {
+    repository.find_path(&["discussionCategories", "nodes"])
+        .and_then(|nodes| nodes.as_array())
+        .and_then(|nodes| nodes.iter().find(|node| node.find("name").and_then(|n| n.as_string()) == Some(name)))
+        .and_then(|node| node.find("id"))
+        .and_then(|id| id.as_string())
+        .map(|id| id.to_owned())
+}
+
+/// Announces a release as a discussion in the given category.
+///
+/// Returns the URL of the discussion, or `None` if discussions are disabled for the repository.
+pub fn create_discussion(config: &Config, category: &str, title: &str, body: &str) -> Result<Option<String>, Error> {
+    let user      = &config.user.as_ref().unwrap()[..];
+    let repo_name = &config.repository_name.as_ref().unwrap()[..];
+
+    let mut variables = BTreeMap::new();
+    variables.insert("owner".to_owned(), Json::String(user.to_owned()));
+    variables.insert("name".to_owned(), Json::String(repo_name.to_owned()));
+    let data = try!(graphql(config, DISCUSSION_CATEGORIES_QUERY, variables));
+
+    let repository = match data.find("repository") {
+        Some(repository) => repository,
+        None => return Err(Error::Api(format!("Repository {}/{} not found", user, repo_name))),
+    };
+
+    if repository.find("hasDiscussionsEnabled").and_then(|e| e.as_boolean()) != Some(true) {
+        return Ok(None);
+    }
+
+    let category_id = match find_category(repository, category) {
+        Some(id) => id,
+        None => return Err(Error::Api(format!("No discussion category named '{}'", category))),
+    };
+    let repository_id = repository.find("id").and_then(|id| id.as_string()).unwrap_or("").to_owned();
+
+    let mut variables = BTreeMap::new();
+    variables.insert("repositoryId".to_owned(), Json::String(repository_id));
+    variables.insert("categoryId".to_owned(), Json::String(category_id));
+    variables.insert("title".to_owned(), Json::String(title.to_owned()));
+    variables.insert("body".to_owned(), Json::String(body.to_owned()));
+    let data = try!(graphql(config, CREATE_DISCUSSION_MUTATION, variables));
+
+    Ok(data.find_path(&["createDiscussion", "discussion", "url"])
+        .and_then(|url| url.as_string())
+        .map(|url| url.to_owned()))
+}
+
+/// Fills in `{version}`, `{major}`, `{minor}` and `{patch}` of a milestone title template.
+pub fn render_milestone_title(template: &str, version: &str) -> String {
+    let (major, minor, patch) = match Version::parse(version) {
+        Ok(v) => (v.major.to_string(), v.minor.to_string(), v.patch.to_string()),
+        Err(_) => (String::new(), String::new(), String::new()),
+    };
+
+    template
+        .replace("{version}", version)
+        .replace("{major}", &major)
+        .replace("{minor}", &minor)
+        .replace("{patch}", &patch)
+}
+
+/// Lists all items of a GitHub collection, following the pagination links.
+fn list(config: &Config, url: &str) -> Result<Vec<Json>, Error> {
+    let headers = auth_headers(config);
+    let mut items = vec![];
+    let mut next = Some(url.to_owned());
+
+    while let Some(url) = next {
+        let (body, next_url) = try!(http::get_page(&url, &headers));
+        if let Json::Array(page) = try!(Json::from_str(&body)) {
+            items.extend(page);
+        }
+        next = next_url;
+    }
+
+    Ok(items)
+}
+
+/// Finds the number of the milestone with the given title in a milestone listing.
+fn find_milestone(milestones: &[Json], title: &str) -> Option<u64> {
+    milestones.iter()
+        .find(|milestone| milestone.find("title").and_then(|t| t.as_string()) == Some(title))
+        .and_then(|milestone| milestone.find("number"))
+        .and_then(|number| number.as_u64())
+}
+
+/// Closes the open milestone with the given title.
+///
+/// Returns whether a matching milestone was found.
+pub fn close_milestone(config: &Config, title: &str) -> Result<bool, Error> {
+    let user      = &config.user.as_ref().unwrap()[..];
+    let repo_name = &config.repository_name.as_ref().unwrap()[..];
+
+    let headers = auth_headers(config);
+    let url = format!("{}/repos/{}/{}/milestones", API_URL, user, repo_name);
+
+    let milestones = try!(list(config, &format!("{}?state=open&per_page=100", url)));
+
+    match find_milestone(&milestones, title) {
+        Some(number) => {
+            let url = format!("{}/{}", url, number);
+            try!(http::send_json(Method::Patch, &url, &headers, r#"{"state":"closed"}"#));
+            Ok(true)
+        },
+        None => Ok(false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{find_category, find_milestone, render_milestone_title};
+    use rustc_serialize::json::Json;
+
+    #[test]
+    fn renders_milestone_title() {
+        assert_eq!("v1.2.3", render_milestone_title("v{version}", "1.2.3"));
+        assert_eq!("1.2", render_milestone_title("{major}.{minor}", "1.2.3"));
+    }
+
+    #[test]
+    fn finds_discussion_category_by_name() {
+        let repository = Json::from_str(r#"{"discussionCategories":{"nodes":[
+            {"id":"DIC_1","name":"General"},{"id":"DIC_2","name":"Announcements"}]}}"#).unwrap();
+        assert_eq!(Some("DIC_2".to_owned()), find_category(&repository, "Announcements"));
+        assert_eq!(None, find_category(&repository, "Releases"));
+    }
+
+    #[test]
+    fn finds_milestone_by_title() {
+        let milestones = Json::from_str(r#"[{"title":"1.1","number":3},{"title":"1.2","number":4}]"#).unwrap();
+        let milestones = milestones.as_array().unwrap();
+        assert_eq!(Some(4), find_milestone(milestones, "1.2"));
+        assert_eq!(None, find_milestone(milestones, "2.0"));
+    }
+}
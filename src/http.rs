@@ -0,0 +1,203 @@
+use hyper::Client;
+use hyper::client::{ProxyConfig, Response};
+use hyper::status::StatusCode;
+use hyper::header::{ContentType, Headers, UserAgent};
+use hyper::method::Method;
+use std::env;
+use std::io::Read;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use hyper::net::{HttpConnector, HttpsConnector};
+use hyper_native_tls::NativeTlsClient;
+use url::Url;
+use error::Error;
+use super::USERAGENT;
+
+/// The read and write timeout of all clients in seconds, 0 for none.
+static TIMEOUT_SECS: AtomicUsize = AtomicUsize::new(0);
+
+/// Applies a read and write timeout to all clients created from now on.
+pub fn set_timeout(seconds: u64) {
+    TIMEOUT_SECS.store(seconds as usize, Ordering::SeqCst);
+}
+
+/// The host and port of a proxy given as `http://host:port` or `host:port`.
+pub fn parse_proxy(proxy: &str) -> Option<(String, u16)> {
+    let proxy = if proxy.contains("://") { proxy.to_owned() } else { format!("http://{}", proxy) };
+    let url = match Url::parse(&proxy) {
+        Ok(url) => url,
+        Err(_) => return None,
+    };
+
+    url.host_str()
+        .map(|host| (host.to_owned(), url.port_or_known_default().unwrap_or(80)))
+}
+
+/// Whether a host is excluded from proxying by a `NO_PROXY` list like `localhost,.example.com`.
+pub fn is_no_proxy(host: &str, no_proxy: &str) -> bool {
+    no_proxy.split(',')
+        .map(|entry| entry.trim().trim_left_matches('.'))
+        .filter(|entry| !entry.is_empty())
+        .any(|entry| entry == "*" || host == entry || host.ends_with(&format!(".{}", entry)))
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names.iter()
+        .filter_map(|name| env::var(name).ok())
+        .find(|value| !value.is_empty())
+}
+
+fn proxy_from_env(url: &str) -> Option<(String, u16)> {
+    let url = match Url::parse(url) {
+        Ok(url) => url,
+        Err(_) => return None,
+    };
+
+    let excluded = match (url.host_str(), env_var(&["NO_PROXY", "no_proxy"])) {
+        (Some(host), Some(no_proxy)) => is_no_proxy(host, &no_proxy),
+        _ => false,
+    };
+    if excluded {
+        return None;
+    }
+
+    let names = if url.scheme() == "https" { ["HTTPS_PROXY", "https_proxy"] } else { ["HTTP_PROXY", "http_proxy"] };
+    env_var(&names).and_then(|proxy| parse_proxy(&proxy))
+}
+
+/// A client for requests to `url`, going through `HTTPS_PROXY` or `HTTP_PROXY` depending on
+/// its scheme, unless its host is listed in `NO_PROXY`.
+pub fn client(url: &str) -> Client {
+    let ssl = NativeTlsClient::new().unwrap();
+    let mut client = match proxy_from_env(url) {
+        Some((host, port)) => Client::with_proxy_config(ProxyConfig::new("http", host, port, HttpConnector, ssl)),
+        None => Client::with_connector(HttpsConnector::new(ssl)),
+    };
+
+    let timeout = TIMEOUT_SECS.load(Ordering::SeqCst);
+    if timeout > 0 {
+        client.set_read_timeout(Some(Duration::from_secs(timeout as u64)));
+        client.set_write_timeout(Some(Duration::from_secs(timeout as u64)));
+    }
+
+    client
+}
+
+pub fn get(url: &str) -> Result<Response, Error> {
+    client(url)
+        .get(url)
+        .header(UserAgent(USERAGENT.to_owned()))
+        .send()
+        .map_err(Error::from)
+}
+
+fn headers(extra_headers: &[(String, String)]) -> Headers {
+    let mut headers = Headers::new();
+    headers.set(UserAgent(USERAGENT.to_owned()));
+    for &(ref name, ref value) in extra_headers {
+        headers.set_raw(name.clone(), vec![value.clone().into_bytes()]);
+    }
+    headers
+}
+
+/// The status of a GET request with additional raw headers, ignoring the body.
+pub fn status(url: &str, extra_headers: &[(String, String)]) -> Result<StatusCode, Error> {
+    let response = try!(client(url)
+        .get(url)
+        .headers(headers(extra_headers))
+        .send());
+
+    Ok(response.status)
+}
+
+/// The URL of the next page from a `Link` header, as used by the GitHub API.
+pub fn next_link(link: &str) -> Option<String> {
+    link.split(',')
+        .filter(|part| part.split(';').skip(1).any(|param| param.trim() == "rel=\"next\""))
+        .filter_map(|part| {
+            let url = part.split(';').next().unwrap_or("").trim();
+            if url.starts_with('<') && url.ends_with('>') {
+                Some(url[1..url.len() - 1].to_owned())
+            } else {
+                None
+            }
+        })
+        .next()
+}
+
+/// Fetches one page of a paginated listing with additional raw headers.
+///
+/// Returns the body and the URL of the next page, if any. Any non-2xx response is an error.
+pub fn get_page(url: &str, extra_headers: &[(String, String)]) -> Result<(String, Option<String>), Error> {
+    let mut response = try!(client(url)
+        .get(url)
+        .headers(headers(extra_headers))
+        .send());
+
+    if !response.status.is_success() {
+        return Err(Error::HttpStatus(response.status));
+    }
+
+    let next = response.headers.get_raw("Link")
+        .and_then(|values| values.iter().filter_map(|v| ::std::str::from_utf8(v).ok()).filter_map(next_link).next());
+
+    let mut body = String::new();
+    try!(response.read_to_string(&mut body));
+    Ok((body, next))
+}
+
+pub fn post_json(url: &str, body: &str) -> Result<(), Error> {
+    send_json(Method::Post, url, &[], body)
+}
+
+/// Sends a JSON body with additional raw headers. Any non-2xx response is an error.
+pub fn send_json(method: Method, url: &str, extra_headers: &[(String, String)], body: &str) -> Result<(), Error> {
+    send_json_for_body(method, url, extra_headers, body).map(|_| ())
+}
+
+/// Like `send_json`, but returns the response body.
+pub fn send_json_for_body(method: Method, url: &str, extra_headers: &[(String, String)], body: &str) -> Result<String, Error> {
+    let mut headers = headers(extra_headers);
+    headers.set(ContentType::json());
+
+    let mut response = try!(client(url)
+        .request(method, url)
+        .headers(headers)
+        .body(body)
+        .send());
+
+    if !response.status.is_success() {
+        return Err(Error::HttpStatus(response.status));
+    }
+
+    let mut body = String::new();
+    try!(response.read_to_string(&mut body));
+    Ok(body)
+}
+
+#[test]
+fn parses_proxies() {
+    assert_eq!(Some(("proxy.example.com".to_owned(), 3128)), parse_proxy("http://proxy.example.com:3128"));
+    assert_eq!(Some(("proxy.example.com".to_owned(), 3128)), parse_proxy("proxy.example.com:3128"));
+    assert_eq!(Some(("10.0.0.1".to_owned(), 80)), parse_proxy("http://10.0.0.1/"));
+}
+
+#[test]
+fn matches_no_proxy_hosts() {
+    assert!(is_no_proxy("localhost", "localhost,127.0.0.1"));
+    assert!(is_no_proxy("api.github.com", "example.com, .github.com"));
+    assert!(is_no_proxy("github.com", ".github.com"));
+    assert!(is_no_proxy("crates.io", "*"));
+    assert!(!is_no_proxy("notgithub.com", "github.com"));
+    assert!(!is_no_proxy("crates.io", ""));
+}
+
+#[test]
+fn finds_next_link() {
+    let link = "<https://api.github.com/repositories/1/milestones?page=2>; rel=\"next\", \
+                <https://api.github.com/repositories/1/milestones?page=5>; rel=\"last\"";
+    assert_eq!(Some("https://api.github.com/repositories/1/milestones?page=2".to_owned()), next_link(link));
+
+    let last_page = "<https://api.github.com/repositories/1/milestones?page=1>; rel=\"first\"";
+    assert_eq!(None, next_link(last_page));
+}
@@ -1,6 +1,17 @@
 use std::convert::AsRef;
 extern crate term;
+use std::collections::BTreeMap;
 use std::io::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+use rustc_serialize::json::Json;
+use atty;
+
+/// The target of the records semantic-rs logs itself, as opposed to those of its dependencies.
+pub const TARGET: &'static str = "semantic-rs";
+
+static JSON_FORMAT: AtomicBool = AtomicBool::new(false);
+static PROGRESS: AtomicBool = AtomicBool::new(false);
 
 pub enum MessageType {
     Info,
@@ -8,6 +19,45 @@ pub enum MessageType {
     Error
 }
 
+impl MessageType {
+    fn level(&self) -> &'static str {
+        match *self {
+            MessageType::Info => "info",
+            MessageType::Warn => "warn",
+            MessageType::Error => "error",
+        }
+    }
+}
+
+/// Switches all further output to one JSON object per line.
+pub fn use_json_format() {
+    JSON_FORMAT.store(true, Ordering::SeqCst);
+}
+
+/// Whether output goes to a terminal we can color, rather than a log file or pipe.
+pub fn is_terminal() -> bool {
+    !JSON_FORMAT.load(Ordering::SeqCst) && atty::is(atty::Stream::Stdout)
+}
+
+/// Marks completed steps with a check and errors with a cross, for interactive runs.
+pub fn use_progress() {
+    PROGRESS.store(true, Ordering::SeqCst);
+}
+
+/// Reports a completed release step. Only shown with progress output.
+pub fn step_done(step: &str) {
+    if !PROGRESS.load(Ordering::SeqCst) {
+        return;
+    }
+
+    let mut terminal = term::stdout().unwrap();
+    terminal.fg(term::color::GREEN).unwrap();
+    write!(terminal, "✔ ").unwrap();
+    terminal.reset().unwrap();
+    writeln!(terminal, "{}", step).unwrap();
+    terminal.flush().unwrap();
+}
+
 pub fn stdout<P: AsRef<str>>(message: P) {
     print_message(message, MessageType::Info);
 }
@@ -20,7 +70,30 @@ pub fn stderr<P: AsRef<str>>(message: P) {
     print_message(message, MessageType::Error);
 }
 
+/// Formats a log record as a JSON object on a single line.
+pub fn json_line(level: &str, target: &str, message: &str) -> String {
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+
+    let mut record = BTreeMap::new();
+    record.insert("level".to_owned(), Json::String(level.into()));
+    record.insert("target".to_owned(), Json::String(target.into()));
+    record.insert("message".to_owned(), Json::String(message.into()));
+    record.insert("timestamp".to_owned(), Json::U64(timestamp));
+    Json::Object(record).to_string()
+}
+
 fn print_message<P: AsRef<str>>(message: P, message_type: MessageType) {
+    if JSON_FORMAT.load(Ordering::SeqCst) {
+        let line = json_line(message_type.level(), TARGET, message.as_ref());
+        match message_type {
+            MessageType::Error => writeln!(::std::io::stderr(), "{}", line).unwrap(),
+            _ => println!("{}", line),
+        }
+        return;
+    }
+
     match message_type {
         MessageType::Info => {
             println!("{}", message.as_ref());
@@ -35,9 +108,29 @@ fn print_message<P: AsRef<str>>(message: P, message_type: MessageType) {
         MessageType::Error => {
             let mut error_terminal = term::stderr().unwrap();
             error_terminal.fg(term::color::RED).unwrap();
+            if PROGRESS.load(Ordering::SeqCst) {
+                write!(error_terminal, "✘ ").unwrap();
+            }
             writeln!(error_terminal, "{}", message.as_ref()).unwrap();
             error_terminal.reset().unwrap();
             error_terminal.flush().unwrap();
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rustc_serialize::json::Json;
+
+    #[test]
+    fn formats_json_lines() {
+        let line = json_line("warn", TARGET, "Bumping \"version\"");
+        let record = Json::from_str(&line).unwrap();
+
+        assert_eq!(Some("warn"), record.find("level").and_then(|l| l.as_string()));
+        assert_eq!(Some("semantic-rs"), record.find("target").and_then(|t| t.as_string()));
+        assert_eq!(Some("Bumping \"version\""), record.find("message").and_then(|m| m.as_string()));
+        assert!(record.find("timestamp").and_then(|t| t.as_u64()).is_some());
+    }
+}
@@ -13,6 +13,14 @@ mod github;
 mod config;
 mod utils;
 mod preflight;
+mod http;
+mod discord;
+mod teams;
+mod webhook;
+mod ci;
+mod version_files;
+mod crates_io;
+mod sbom;
 
 extern crate rustc_serialize;
 extern crate toml;
@@ -25,21 +33,26 @@ extern crate hubcaps;
 extern crate url;
 extern crate travis_after_all;
 extern crate env_logger;
+extern crate log;
 extern crate hyper_native_tls;
 extern crate clap;
+extern crate atty;
 
-use clap::{Arg, ArgMatches, App};
-use commit_analyzer::CommitType;
+use clap::{Arg, ArgMatches, App, ErrorKind, SubCommand};
+use commit_analyzer::{self as analyzer, CommitType};
 use config::ConfigBuilder;
 use std::process;
 use semver::Version;
 use std::{env,fs};
 use std::path::Path;
+use std::io::Read;
 use std::error::Error;
-use std::thread;
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use travis_after_all::Build;
+use env_logger::LogBuilder;
+use log::LogLevelFilter;
 use utils::user_repo_from_url;
+use version_files::VersionReplacement;
 
 const VERSION: &'static str = env!("CARGO_PKG_VERSION");
 const USERAGENT: &'static str = concat!("semantic-rs/", env!("CARGO_PKG_VERSION"));
@@ -57,7 +70,10 @@ If none is set the normal git config is tried in the following order:
 
 Local repository config
 User config
-Global config";
+Global config
+
+On GitHub Actions, GITHUB_ACTOR or the GitHub Actions bot is used last.
+The order can be changed with --committer-source and a fallback given with --default-committer.";
 
 macro_rules! print_exit {
     ($fmt:expr) => {{
@@ -89,15 +105,60 @@ fn version_bump(version: &Version, bump: CommitType) -> Option<Version> {
     Some(version)
 }
 
-fn ci_env_set() -> bool {
-    env::var("CI").is_ok()
+fn apply_build_metadata(config: &config::Config, version: Version) -> Version {
+    let template = match config.build_metadata {
+        Some(ref template) => template,
+        None => return version
+    };
+
+    let sha = git::short_head_sha(&config.repository)
+        .unwrap_or_else(|| print_exit!("Could not determine the current commit for build metadata"));
+    let build_number = config.ci_provider.and_then(ci::build_number);
+    let metadata = utils::render_build_metadata(template, &sha, build_number.as_ref().map(|b| &b[..]))
+        .unwrap_or_else(|err| print_exit!("{}", err));
+
+    Version::parse(&format!("{}+{}", version, metadata))
+        .unwrap_or_else(|_| print_exit!("'{}' is not valid build metadata", metadata))
+}
+
+fn zero_version_bump(config: &config::Config, version: &Version, bump: CommitType) -> CommitType {
+    if version.major != 0 {
+        return bump;
+    }
+
+    let mapped = config.zero_version.map_bump(bump);
+    if mapped != bump {
+        logger::stdout(format!("Version is below 1.0.0, bumping {:?} instead of {:?}", mapped, bump));
+    }
+    mapped
+}
+
+fn cap_bump(config: &config::Config, bump: CommitType) -> CommitType {
+    match config.max_bump {
+        Some(max_bump) if bump > max_bump => {
+            logger::stdout(format!("Capping bump from {:?} to {:?}", bump, max_bump));
+            max_bump
+        },
+        _ => bump
+    }
+}
+
+/// The CI service to act for, detected unless forced on or off with `--ci`.
+fn ci_provider(args: &ArgMatches) -> Option<ci::Provider> {
+    match args.value_of("ci") {
+        Some("auto") | None => ci::detect(),
+        Some(ci) if string_to_bool(ci) => Some(ci::detect().unwrap_or(ci::Provider::Generic)),
+        Some(_) => None,
+    }
 }
 
-fn current_branch(repo: &git2::Repository) -> Option<String> {
-    if let Ok(branch) = env::var("TRAVIS_BRANCH") {
+fn current_branch(config: &config::Config) -> Option<String> {
+    if let Some(branch) = config.ci_provider.and_then(ci::branch) {
         return Some(branch)
     }
 
+    let repo = &config.repository;
+
     let head = repo.head().expect("No HEAD found for repository");
 
     if head.is_branch() {
@@ -105,49 +166,314 @@ fn current_branch(repo: &git2::Repository) -> Option<String> {
         return Some(short.into());
     }
 
+    if config.explicit_branch {
+        return Some(config.branch.clone());
+    }
+
     None
 }
 
 fn is_release_branch(current: &str, release: &str) -> bool {
-    if let Ok(pr) = env::var("TRAVIS_PULL_REQUEST") {
-        if pr != "false" {
-            return false;
-        }
-    }
-
     current == release
 }
 
-fn push_to_github(config: &config::Config, tag_name: &str) {
-    logger::stdout("Pushing new commit and tag");
-    git::push(&config, &tag_name)
+fn push_to_github(config: &config::Config, branch: &str, tag_name: Option<&str>) {
+    match tag_name {
+        Some(_) => logger::stdout("Pushing new commit and tag"),
+        None => logger::stdout("Pushing new commit"),
+    }
+    git::push(&config, &branch, tag_name)
         .unwrap_or_else(|err| print_exit!("Failed to push git: {:?}", err));
+}
 
-    logger::stdout("Waiting a tiny bit, so GitHub can store the git tag");
-    thread::sleep(Duration::from_secs(1));
+/// The notes for the GitHub release: the matching section of the configured changelog
+/// if there is one, the generated changelog otherwise.
+fn release_notes(config: &config::Config, version: &str, generated: &str) -> String {
+    let file = match config.notes_from_changelog {
+        Some(ref file) => file,
+        None => return generated.to_owned(),
+    };
+
+    let content = fs::File::open(Path::new(&config.repository_path).join(file))
+        .and_then(|mut f| {
+            let mut content = String::new();
+            f.read_to_string(&mut content).map(|_| content)
+        });
+
+    match content.map(|content| changelog::extract_section(&content, version)) {
+        Ok(Some(section)) => section,
+        Ok(None) => {
+            logger::warn(format!("No section for {} found in `{}`, using the generated notes", version, file));
+            generated.to_owned()
+        },
+        Err(err) => {
+            logger::warn(format!("Reading `{}` failed, using the generated notes: {:?}", file, err));
+            generated.to_owned()
+        }
+    }
 }
 
-fn release_on_github(config: &config::Config, tag_message: &str, tag_name: &str) {
+fn release_on_github(config: &config::Config, new_version: &str, tag_message: &str, tag_name: &str) {
     if github::can_release(&config) {
+        logger::stdout(format!("Waiting up to {}s for GitHub to show the tag", config.tag_wait_timeout));
+        let visible = github::wait_for_tag(&config, &tag_name, Duration::from_secs(config.tag_wait_timeout))
+            .unwrap_or_else(|err| print_exit!("Failed to look up the tag on GitHub: {:?}", err));
+        if !visible {
+            print_exit!("Tag {} was not visible on GitHub after {}s", tag_name, config.tag_wait_timeout);
+        }
+
         logger::stdout("Creating GitHub release");
-        github::release(&config, &tag_name, &tag_message)
+        let notes = release_notes(config, new_version, tag_message);
+        github::release(&config, &new_version, &tag_name, &notes)
             .unwrap_or_else(|err| print_exit!("Failed to create GitHub release: {:?}", err));
     } else {
         logger::stdout("Project not hosted on GitHub. Skipping release step");
     }
 }
 
-fn release_on_cratesio(config: &config::Config) {
-    logger::stdout("Publishing crate on crates.io");
-    if !cargo::publish(&config.repository_path, &config.cargo_token.as_ref().unwrap()) {
-        print_exit!("Failed to publish on crates.io");
+fn announce_discussion(config: &config::Config, category: &str, new_version: &str, tag_message: &str, tag_name: &str) {
+    logger::stdout(format!("Announcing release in the '{}' discussions", category));
+    let name = config.repository_name.as_ref().map(|n| &n[..]).unwrap_or("");
+    let title = format!("{} {}", name, tag_name);
+    let notes = release_notes(config, new_version, tag_message);
+
+    match github::create_discussion(config, category, &title, &notes) {
+        Ok(Some(url)) => logger::stdout(format!("Created discussion {}", url)),
+        Ok(None) => logger::warn("Discussions are disabled for this repository. Skipping the announcement"),
+        Err(err) => print_exit!("Failed to create discussion: {:?}", err),
+    }
+}
+
+fn close_milestone(config: &config::Config, template: &str, new_version: &str) {
+    let title = github::render_milestone_title(template, new_version);
+    logger::stdout(format!("Closing milestone '{}'", title));
+    match github::close_milestone(&config, &title) {
+        Ok(true) => {},
+        Ok(false) => logger::warn(format!("No open milestone named '{}' found", title)),
+        Err(err) => print_exit!("Failed to close milestone: {:?}", err),
+    }
+}
+
+fn open_pull_request(config: &config::Config, branch: &str, tag_message: &str, tag_name: &str) {
+    if github::can_release(&config) {
+        logger::stdout(format!("Opening pull request from '{}' into '{}'", branch, config.branch));
+        github::open_pull_request(&config, &branch, &tag_name, &tag_message)
+            .unwrap_or_else(|err| print_exit!("Failed to open pull request: {:?}", err));
+    } else {
+        logger::warn(format!("Project not hosted on GitHub. Open a pull request from '{}' into '{}' manually", branch, config.branch));
+    }
+}
+
+fn release_on_cratesio(config: &config::Config, new_version: &str) {
+    logger::stdout(format!("Publishing crate on {}", config.registry_name()));
+    let registry = config.registry.as_ref().map(|r| &r[..]);
+    if !cargo::publish(&config.repository_path, &config.cargo_token.as_ref().unwrap(), registry, &config.publish_args, config.command_timeout()) {
+        print_exit!("Failed to publish on {}. See above for the cargo error message.", config.registry_name());
+    }
+
+    if config.registry.is_some() {
+        if config.wait_for_index.is_some() {
+            logger::warn("--wait-for-index only supports crates.io. Not waiting for the registry");
+        }
+        return;
+    }
+
+    if let Some(seconds) = config.wait_for_index {
+        wait_for_cratesio_index(config, new_version, seconds);
+    }
+}
+
+fn wait_for_cratesio_index(config: &config::Config, new_version: &str, seconds: u64) {
+    let name = toml_file::read_name_from_file(&config.repository_path)
+        .unwrap_or_else(|err| print_exit!("Reading `Cargo.toml` failed: {:?}", err));
+
+    logger::stdout(format!("Waiting up to {}s for crates.io to serve {} v{}", seconds, name, new_version));
+    let available = crates_io::wait_until_published(&name, new_version, Duration::from_secs(seconds))
+        .unwrap_or_else(|err| print_exit!("Failed to query crates.io: {:?}", err));
+
+    if !available {
+        print_exit!("{} v{} was not available on crates.io after {}s", name, new_version, seconds);
+    }
+}
+
+/// The replacements for the `--also-bump` files followed by the configured ones.
+fn version_replacements(config: &config::Config, version: &Version) -> Vec<VersionReplacement> {
+    let mut replacements = Vec::new();
+
+    if !config.also_bump.is_empty() {
+        let name = toml_file::read_name_from_file(&config.repository_path).ok()
+            .or_else(|| config.repository_name.clone())
+            .unwrap_or_else(|| print_exit!("--also-bump needs the package name to find the version. Use --version-replace instead"));
+        let old_version = version.to_string();
+
+        replacements.extend(config.also_bump.iter()
+            .map(|file| version_files::package_version_replacement(file, &name, &old_version)));
+    }
+
+    replacements.extend(config.version_replacements.iter().cloned());
+    replacements
+}
+
+fn bump_additional_files(config: &config::Config, version: &Version, new_version: &str) {
+    for replacement in version_replacements(config, version) {
+        logger::stdout(format!("Updating version in {} using `{}`", replacement.file, replacement.pattern));
+        version_files::write_replacement(&config.repository_path, &replacement, new_version)
+            .unwrap_or_else(|err| print_exit!("Writing `{}` failed: {:?}", replacement.file, err));
+    }
+}
+
+fn print_version_replacement_diffs(config: &config::Config, version: &Version, new_version: &str) {
+    for replacement in version_replacements(config, version) {
+        let (content, new_content) = version_files::preview_replacement(&config.repository_path, &replacement, new_version)
+            .unwrap_or_else(|err| print_exit!("Reading `{}` failed: {:?}", replacement.file, err));
+
+        logger::stdout(format!("Would change `{}`:", replacement.file));
+        logger::stdout(utils::line_diff(&content, &new_content));
+    }
+}
+
+fn generate_sbom(config: &config::Config, new_version: &str) {
+    let (command, file) = match (&config.sbom_command, config.sbom_file_for(new_version)) {
+        (&Some(ref command), Some(file)) => (command, file),
+        _ => return,
+    };
+
+    logger::stdout(format!("Generating SBOM {}", file));
+    if !sbom::generate(&config.repository_path, command, &file, new_version, config.command_timeout()) {
+        print_exit!("Generating the SBOM failed, timed out or didn't create `{}`", file);
+    }
+}
+
+fn notify_discord(config: &config::Config, tag_message: &str, tag_name: &str) {
+    logger::stdout("Announcing release on Discord");
+    let webhook_url = config.discord_webhook.as_ref().unwrap();
+    let name = config.repository_name.as_ref().map(|n| &n[..]).unwrap_or("");
+    discord::notify(webhook_url, name, tag_name, tag_message)
+        .unwrap_or_else(|err| print_exit!("Failed to notify Discord: {:?}", err));
+}
+
+/// Only links the GitHub release if one was created.
+fn notify_teams(config: &config::Config, tag_message: &str, tag_name: &str, released_on_github: bool) {
+    logger::stdout("Announcing release on Microsoft Teams");
+    let webhook_url = config.teams_webhook.as_ref().unwrap();
+    let name = config.repository_name.as_ref().map(|n| &n[..]).unwrap_or("");
+    let release_url = match (&config.user, &config.repository_name) {
+        (&Some(ref user), &Some(ref repo_name)) if released_on_github => {
+            Some(format!("https://github.com/{}/{}/releases/tag/{}", user, repo_name, tag_name))
+        },
+        _ => None,
+    };
+    teams::notify(webhook_url, name, tag_name, tag_message, release_url.as_ref().map(|u| &u[..]))
+        .unwrap_or_else(|err| print_exit!("Failed to notify Teams: {:?}", err));
+}
+
+/// Runs all checks that have to pass before anything is written.
+///
+/// Instead of stopping at the first problem, every reason blocking the release is reported.
+fn verify_release(config: &config::Config, new_version: &str) {
+    let mut blockers = vec![];
+
+    if let Some(blocker) = verify_clean_working_tree(config) {
+        blockers.push(blocker);
+    }
+
+    if let Some(blocker) = verify_commit_format(config) {
+        blockers.push(blocker);
+    }
+
+    // Only crates.io can be asked whether a version exists without credentials.
+    if config.release_mode && config.can_release_to_cratesio() && config.registry.is_none() {
+        if let Some(blocker) = verify_unpublished_on_cratesio(config, new_version) {
+            blockers.push(blocker);
+        }
+    }
+
+    if !blockers.is_empty() {
+        logger::stderr("Release blocked by:");
+        for blocker in blockers {
+            logger::stderr(format!(">> {}", blocker));
+        }
+        process::exit(1);
+    }
+}
+
+fn verify_clean_working_tree(config: &config::Config) -> Option<String> {
+    let files = match git::unexpected_changes(&config) {
+        Ok(files) => files,
+        Err(err) => return Some(format!("Failed to determine the working tree status: {:?}", err))
+    };
+
+    if files.is_empty() {
+        return None;
+    }
+
+    let message = format!("The working tree has changes that are not part of a release: {}", files.join(", "));
+    if config.allow_dirty {
+        logger::warn(message);
+        None
+    } else {
+        Some(format!("{}. Commit or remove them first, or pass --allow-dirty=yes.", message))
+    }
+}
+
+/// Commits not following the preset are silently ignored when choosing the version,
+/// so they are pointed out here.
+fn verify_commit_format(config: &config::Config) -> Option<String> {
+    let commits = git::nonconforming_commits(config);
+    if commits.is_empty() {
+        return None;
+    }
+
+    let message = format!("Commits not following the {} format: {}", config.preset.as_str(), commits.join(", "));
+    if config.strict_commits {
+        Some(message)
+    } else {
+        logger::warn(message);
+        None
+    }
+}
+
+fn notify_webhook(config: &config::Config, new_version: &str, bump: CommitType, tag_message: &str, tag_name: &str) {
+    logger::stdout("Notifying webhook");
+    let url = config.webhook_url.as_ref().unwrap();
+    let body = webhook::render_body(&config.webhook_body, new_version, tag_name, bump, tag_message);
+    webhook::notify(url, &config.webhook_method, &config.webhook_headers, &body)
+        .unwrap_or_else(|err| print_exit!("Failed to notify webhook: {:?}", err));
+}
+
+fn verify_unpublished_on_cratesio(config: &config::Config, new_version: &str) -> Option<String> {
+    let name = match toml_file::read_name_from_file(&config.repository_path) {
+        Ok(name) => name,
+        Err(err) => return Some(format!("Reading `Cargo.toml` failed: {:?}", err))
+    };
+
+    match crates_io::is_published(&name, new_version) {
+        Ok(true) => Some(format!("{} v{} is already published on crates.io", name, new_version)),
+        Ok(false) => None,
+        Err(err) => Some(format!("Failed to query crates.io: {:?}", err)),
+    }
+}
+
+fn fetch_tags(config: &config::Config) {
+    logger::stdout("Fetching tags");
+    if let Err(err) = git::fetch_tags(&config) {
+        logger::warn(format!("Failed to fetch tags, continuing with the local ones: {:?}", err));
     }
 }
 
-fn generate_changelog(repository_path: &str, version: &Version, new_version: &String) -> String {
+/// The heading of the changelog entry for the new version.
+fn version_heading(config: &config::Config, new_version: &str) -> String {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH)
+        .expect("System time is before the unix epoch")
+        .as_secs() as i64;
+    let date = utils::date_from_timestamp(now, config.date_utc_offset);
+    utils::render_version_heading(&config.version_heading, new_version, date)
+}
+
+fn generate_changelog(config: &config::Config, from: &str, new_version: &str, heading: &str) -> String {
     logger::stdout(format!("New version would be: {}", new_version));
     logger::stdout("Would write the following Changelog:");
-    match changelog::generate(repository_path, &version.to_string(), new_version) {
+    match changelog::generate(&config.repository_path, from, heading, config.ignore_merges) {
         Ok(log) => log,
         Err(err) => {
             logger::stderr(format!("Generating Changelog failed: {:?}", err));
@@ -156,12 +482,116 @@ fn generate_changelog(repository_path: &str, version: &Version, new_version: &St
     }
 }
 
-fn write_changelog(repository_path: &str, version: &Version, new_version: &str) {
+fn write_changelog(config: &config::Config, from: &str, heading: &str) {
     logger::stdout("Writing Changelog");
-    changelog::write(repository_path, &version.to_string(), &new_version)
+    changelog::write(&config.repository_path, from, heading, config.ignore_merges)
         .unwrap_or_else(|err| print_exit!("Writing Changelog failed: {:?}", err));
 }
 
+fn ignore_merges(args: &ArgMatches) -> bool {
+    match args.value_of("ignore-merges") {
+        Some(ignore_merges) => string_to_bool(ignore_merges),
+        None => true
+    }
+}
+
+fn print_notes(args: &ArgMatches, notes_args: &ArgMatches) {
+    let repository_path = get_repository_path(args);
+    let since = notes_args.value_of("since").unwrap();
+    let to = notes_args.value_of("to").unwrap_or("HEAD");
+
+    let notes = changelog::generate_range(&repository_path, since, to, ignore_merges(args))
+        .unwrap_or_else(|err| print_exit!("Generating Changelog failed: {:?}", err));
+
+    logger::stdout(notes);
+}
+
+/// Creates the GitHub release for a tag that was already pushed, without committing or tagging.
+fn release_existing_tag(config: &config::Config, tag_name: Option<String>) {
+    if !config.can_release_to_github() || !github::can_release(&config) {
+        print_exit!("Creating a GitHub release needs GH_TOKEN and a GitHub origin remote");
+    }
+
+    let tag_name = tag_name.unwrap_or_else(|| {
+        match git::latest_tag(&config.repository, config.ignore_prereleases) {
+            Some(version) => format!("v{}", version),
+            None => print_exit!("No release tag found"),
+        }
+    });
+
+    let version = tag_name.trim_left_matches('v').to_owned();
+
+    // A release pull request is only tagged once it is merged.
+    if !git::tag_exists(&config.repository, &tag_name) {
+        tag_merged_release(config, &version, &tag_name);
+    }
+
+    let tag_message = match git::tag_message(&config.repository, &tag_name) {
+        Ok(Some(message)) => message,
+        Ok(None) => {
+            logger::warn(format!("{} is a lightweight tag, creating the release without notes", tag_name));
+            String::new()
+        },
+        Err(err) => print_exit!("Failed to read tag {}: {:?}", tag_name, err),
+    };
+
+    logger::stdout(format!("Creating GitHub release for existing tag {}", tag_name));
+    let notes = release_notes(config, &version, &tag_message);
+    github::release(&config, &version, &tag_name, &notes)
+        .unwrap_or_else(|err| print_exit!("Failed to create GitHub release: {:?}", err));
+}
+
+/// Tags the merge commit of a release pull request and pushes the tag.
+///
+/// The annotation is the changelog entry the pull request added.
+fn tag_merged_release(config: &config::Config, version: &str, tag_name: &str) {
+    match current_branch(&config) {
+        Some(ref branch) if is_release_branch(branch, &config.branch) => {},
+        _ => print_exit!("{} doesn't exist yet. Check out the merged release branch '{}' to create it", tag_name, config.branch),
+    }
+
+    let changelog = fs::File::open(Path::new(&config.repository_path).join("Changelog.md"))
+        .and_then(|mut f| {
+            let mut content = String::new();
+            f.read_to_string(&mut content).map(|_| content)
+        })
+        .unwrap_or_else(|err| print_exit!("Reading `Changelog.md` failed: {:?}", err));
+    let entry = changelog::extract_section(&changelog, version)
+        .unwrap_or_else(|| print_exit!("No section for {} found in `Changelog.md`", version));
+
+    let annotation = match config.tag_message_template {
+        Some(ref template) => git::render_tag_message(template, version, tag_name),
+        None => entry,
+    };
+
+    logger::stdout(format!("Tagging the merged release as {}", tag_name));
+    git::tag_head(&config, tag_name, &annotation)
+        .unwrap_or_else(|err| print_exit!("Failed to create git tag: {:?}", err));
+    git::push_tag(&config, tag_name)
+        .unwrap_or_else(|err| print_exit!("Failed to push git tag: {:?}", err));
+}
+
+fn print_manifest_diff(repository_path: &str, new_version: &str) {
+    let (cargo_toml, new_cargo_toml) = toml_file::preview_new_version(repository_path, new_version)
+        .unwrap_or_else(|err| print_exit!("Reading `Cargo.toml` failed: {:?}", err));
+
+    logger::stdout("Would change `Cargo.toml`:");
+    logger::stdout(utils::line_diff(&cargo_toml, &new_cargo_toml));
+}
+
+fn print_version_file_diff(file: &str, version: &str, new_version: &str) {
+    logger::stdout(format!("Would change `{}`:", file));
+    logger::stdout(utils::line_diff(&format!("{}\n", version), &format!("{}\n", new_version)));
+}
+
+fn print_changelog_diff(config: &config::Config, from: &str, heading: &str) {
+    let (changelog, new_changelog) = changelog::preview(&config.repository_path, from, heading, config.ignore_merges)
+        .unwrap_or_else(|err| print_exit!("Generating Changelog failed: {:?}", err));
+
+    logger::stdout("Would change `Changelog.md`:");
+    logger::stdout(utils::line_diff(&changelog, &new_changelog));
+}
+
 fn print_changelog(changelog: &str) {
     logger::stdout("====================================");
     logger::stdout(changelog);
@@ -169,23 +599,53 @@ fn print_changelog(changelog: &str) {
     logger::stdout("Would create annotated git tag");
 }
 
-fn package_crate(config: &config::Config, repository_path: &str, new_version: &str) {
-    if config.release_mode {
+fn package_crate(config: &config::Config, repository_path: &str, branch: &str, new_version: &str) {
+    if config.release_mode && config.is_cargo_project() {
         logger::stdout("Updating lockfile");
-        if !cargo::update_lockfile(repository_path) {
-            print_exit!("`cargo fetch` failed. See above for the cargo error message.");
+        if !cargo::update_lockfile(repository_path, config.command_timeout()) {
+            print_exit!("`cargo fetch` failed or timed out. See above for the cargo error message.");
         }
     }
 
-    git::commit_files(&config, &new_version)
+    git::commit_files(&config, &branch, &new_version)
         .unwrap_or_else(|err| print_exit!("Committing files failed: {:?}", err));
 
+    if config.skips("package") || !config.is_cargo_project() {
+        return;
+    }
+
     logger::stdout("Package crate");
-    if !cargo::package(repository_path) {
-        print_exit!("`cargo package` failed. See above for the cargo error message.");
+    if !cargo::package(repository_path, config.registry.as_ref().map(|r| &r[..]), &config.package_args, config.command_timeout()) {
+        print_exit!("`cargo package` failed or timed out. See above for the cargo error message.");
     }
 }
 
+/// Runs and prints the preflight checks. Returns whether the release can go ahead.
+fn run_preflight_checks(config: &config::Config) -> bool {
+    logger::stdout("Performing preflight checks now");
+    let report = preflight::check(config);
+
+    if report.is_empty() {
+        logger::stdout("Checks done. Everything is ok");
+    }
+
+    for (area, warnings) in preflight::group_by_area(&report.warnings) {
+        logger::warn(format!("{}:", area));
+        for warning in warnings {
+            logger::warn(format!(">> {}", warning));
+        }
+    }
+
+    for (area, errors) in preflight::group_by_area(&report.errors) {
+        logger::stderr(format!("{}:", area));
+        for error in errors {
+            logger::stderr(format!(">> {}", error));
+        }
+    }
+
+    !report.has_errors()
+}
+
 fn get_repo(repository_path: &str) -> git2::Repository {
     match git2::Repository::open(repository_path) {
         Ok(repo) => repo,
@@ -205,9 +665,11 @@ fn get_repository_path(matches: &ArgMatches) -> String {
     repo_path.to_string()
 }
 
-fn get_signature<'a>(repository_path: String) -> git2::Signature<'a> {
+fn get_signature(repository_path: String, sources: &[git::CommitterSource], default: Option<&(String, String)>)
+    -> git2::Signature<'static>
+{
     let repo = get_repo(&repository_path);
-    let signature = match git::get_signature(&repo) {
+    let signature = match git::get_signature(&repo, sources, default) {
         Ok(sig) => sig,
             Err(e) => {
                 logger::stderr(format!("Failed to get the committer's name and email address: {}", e.description()));
@@ -238,6 +700,16 @@ fn get_user_and_repo(repository_path: &str) -> Option<(String, String)> {
     }
 }
 
+/// Reads a token from its variable or the file its `_FILE` variable points to.
+fn get_secret(name: &str) -> Option<String> {
+    let read_file = |path: &str| {
+        let mut contents = String::new();
+        fs::File::open(path).and_then(|mut file| file.read_to_string(&mut contents)).map(|_| contents)
+    };
+    utils::secret_from_env(name, |name| env::var(name).ok(), read_file)
+        .unwrap_or_else(|err| print_exit!("{}", err))
+}
+
 fn get_github_token(repository_path: &str) -> Option<String> {
     let repo = get_repo(repository_path);
     let remote_or_none = repo.find_remote("origin");
@@ -245,7 +717,7 @@ fn get_github_token(repository_path: &str) -> Option<String> {
         Ok(remote) => {
             let url = remote.url().expect("Remote URL is not valid UTF-8").to_owned();
             if github::is_github_url(&url) {
-                env::var("GH_TOKEN").ok()
+                get_secret("GH_TOKEN")
             } else {
                 None
             }
@@ -254,19 +726,47 @@ fn get_github_token(repository_path: &str) -> Option<String> {
     }
 }
 
-fn get_cargo_token() -> Option<String> {
-    env::var("CARGO_TOKEN").ok()
+fn get_cargo_token(registry: Option<&str>) -> Option<String> {
+    match registry {
+        Some(registry) => get_secret(&cargo::registry_token_variable(registry)),
+        None => get_secret("CARGO_TOKEN")
+    }
+}
+
+fn get_ssh_key() -> Option<String> {
+    env::var("GIT_SSH_KEY").ok()
+}
+
+fn get_ssh_key_passphrase() -> Option<String> {
+    env::var("GIT_SSH_KEY_PASSPHRASE").ok()
+}
+
+fn get_discord_webhook() -> Option<String> {
+    env::var("DISCORD_WEBHOOK").ok()
+}
+
+fn get_teams_webhook() -> Option<String> {
+    env::var("TEAMS_WEBHOOK").ok()
 }
 
 fn assemble_configuration(args: ArgMatches) -> config::Config {
     let mut config_builder = ConfigBuilder::new();
 
     // If write mode is requested OR denied,
-    // adhere to the user's wish,
+    // adhere to the user's wish, with --dry-run/--no-dry-run taking precedence over --write,
     // otherwise we decide based on whether we are running in CI.
-    let write_mode = match args.value_of("write") {
-        Some(write_mode) => string_to_bool(write_mode),
-        None => ci_env_set()
+    let ci_provider = ci_provider(&args);
+    let (write_mode, reason) = if args.is_present("dry-run") {
+        (false, "--dry-run".to_owned())
+    } else if args.is_present("no-dry-run") {
+        (true, "--no-dry-run".to_owned())
+    } else if let Some(write_mode) = args.value_of("write") {
+        (string_to_bool(write_mode), format!("--write={}", write_mode))
+    } else {
+        match ci_provider {
+            Some(provider) => (true, format!("running on {}", provider.name())),
+            None => (false, "not running on CI".to_owned()),
+        }
     };
 
     let release_flag = match args.value_of("release") {
@@ -278,11 +778,180 @@ fn assemble_configuration(args: ArgMatches) -> config::Config {
     let release_mode = write_mode && release_flag;
     let repository_path = get_repository_path(&args);
 
+    let pull_request_mode = match args.value_of("pull-request") {
+        Some(pull_request_mode) => string_to_bool(pull_request_mode),
+        None => false
+    };
+
     config_builder.write(write_mode);
+    config_builder.write_mode_reason(reason);
     config_builder.release(release_mode);
+    config_builder.pull_request(pull_request_mode);
+    config_builder.ignore_prereleases(match args.value_of("ignore-prereleases") {
+        Some(ignore) => string_to_bool(ignore),
+        None => false
+    });
+    if let Some(prerelease) = args.value_of("prerelease") {
+        config_builder.github_prerelease(string_to_bool(prerelease));
+    }
+    if let Some(max_bump) = args.value_of("max-bump") {
+        let max_bump = analyzer::parse_bump(max_bump)
+            .unwrap_or_else(|| print_exit!("--max-bump expects one of major, minor or patch"));
+        config_builder.max_bump(max_bump);
+    }
+    if let Some(steps) = args.value_of("skip") {
+        let steps = steps.split(',')
+            .map(|step| step.trim().to_string())
+            .filter(|step| !step.is_empty())
+            .collect::<Vec<_>>();
+        config::check_skipped_steps(&steps)
+            .unwrap_or_else(|err| print_exit!("Invalid --skip: {}", err));
+        for step in steps {
+            config_builder.skip(step);
+        }
+    }
+    // Without pushing, the release is only prepared locally and pushed by hand later.
+    if args.value_of("push").map(string_to_bool) == Some(false) {
+        let skipped = args.value_of("skip").unwrap_or("");
+        let skips = |step: &str| skipped.split(',').any(|s| s.trim() == step);
+
+        if !skips("push") {
+            config_builder.skip("push".into());
+        }
+        if !skips("github") {
+            logger::warn("Not pushing, so the GitHub release and pull request are skipped as well");
+            config_builder.skip("github".into());
+        }
+    }
+    // Fetch tags by default on CI, where clones often come without them.
+    config_builder.fetch_tags(match args.value_of("fetch-tags") {
+        Some(fetch) => string_to_bool(fetch),
+        None => ci_provider.is_some()
+    });
+    config_builder.ci_provider(ci_provider);
+    // An empty --skip-ci-token leaves the release commit message untouched.
+    match args.value_of("skip-ci-token") {
+        Some("") => {},
+        Some(token) => { config_builder.skip_ci_token(token.to_string()); },
+        None => if let Some(provider) = ci_provider {
+            config_builder.skip_ci_token(provider.skip_ci_token().to_string());
+        },
+    }
+    if let Some(behavior) = args.value_of("zero-version") {
+        let behavior = analyzer::parse_zero_version(behavior)
+            .unwrap_or_else(|| print_exit!("--zero-version expects one of standard, semver-default or strict"));
+        config_builder.zero_version(behavior);
+    }
+    if let Some(preset) = args.value_of("preset") {
+        let preset = analyzer::parse_preset(preset)
+            .unwrap_or_else(|| print_exit!("--preset expects one of conventionalcommits, angular or atom"));
+        config_builder.preset(preset);
+    }
+    config_builder.squash_bodies(match args.value_of("squash-bodies") {
+        Some(squash_bodies) => string_to_bool(squash_bodies),
+        None => false
+    });
+    config_builder.ignore_merges(ignore_merges(&args));
+    if let Some(template) = args.value_of("version-heading") {
+        config_builder.version_heading(template.to_string());
+    }
+    if let Some(hours) = args.value_of("date-utc-offset") {
+        let hours = hours.parse()
+            .unwrap_or_else(|_| print_exit!("--date-utc-offset expects a number of hours"));
+        config_builder.date_utc_offset(hours);
+    }
+    if let Some(file) = args.value_of("notes-from-changelog") {
+        config_builder.notes_from_changelog(file.to_string());
+    }
+    if let Some(file) = args.value_of("version-file") {
+        config_builder.version_file(file.to_string());
+    }
+    if let Some(template) = args.value_of("tag-message") {
+        config_builder.tag_message_template(template.to_string());
+    }
+    if let Some(command) = args.value_of("sbom-command") {
+        let file = args.value_of("sbom-file")
+            .unwrap_or_else(|| print_exit!("--sbom-command needs --sbom-file to know which file to commit"));
+        config_builder.sbom(command.to_string(), file.to_string());
+    }
+    if let Some(seconds) = args.value_of("http-timeout") {
+        let seconds = seconds.parse()
+            .unwrap_or_else(|_| print_exit!("--http-timeout expects a number of seconds"));
+        config_builder.http_timeout(seconds);
+    }
+    if let Some(seconds) = args.value_of("command-timeout") {
+        let seconds = seconds.parse()
+            .unwrap_or_else(|_| print_exit!("--command-timeout expects a number of seconds"));
+        config_builder.command_timeout(seconds);
+    }
+    if let Some(seconds) = args.value_of("tag-wait-timeout") {
+        let seconds = seconds.parse()
+            .unwrap_or_else(|_| print_exit!("--tag-wait-timeout expects a number of seconds"));
+        config_builder.tag_wait_timeout(seconds);
+    }
+    if let Some(seconds) = args.value_of("wait-for-index") {
+        let seconds = seconds.parse()
+            .unwrap_or_else(|_| print_exit!("--wait-for-index expects a number of seconds"));
+        config_builder.wait_for_index(seconds);
+    }
+    if let Some(specs) = args.values_of("version-replace") {
+        for spec in specs {
+            let replacement = version_files::parse_version_replacement(spec)
+                .unwrap_or_else(|err| print_exit!("Invalid --version-replace: {}", err));
+            config_builder.version_replacement(replacement);
+        }
+    }
+    if let Some(files) = args.values_of("also-bump") {
+        for file in files {
+            config_builder.also_bump(file.to_string());
+        }
+    }
+    if let Some(variables) = args.values_of("pull-request-variable") {
+        for variable in variables {
+            let (name, value) = ci::parse_variable(variable);
+            config_builder.pull_request_variable(name, value);
+        }
+    }
+    config_builder.run_hooks(match args.value_of("run-hooks") {
+        Some(run_hooks) => string_to_bool(run_hooks),
+        None => false
+    });
+    config_builder.allow_dirty(match args.value_of("allow-dirty") {
+        Some(allow) => string_to_bool(allow),
+        None => false
+    });
+    config_builder.strict_commits(match args.value_of("strict-commits") {
+        Some(strict) => string_to_bool(strict),
+        None => false
+    });
+    if let Some(path) = args.value_of("only-path") {
+        config_builder.path_filter(path.to_string());
+    }
+    if let Some(since) = args.value_of("since") {
+        config_builder.since(since.to_string());
+    }
+    if let Some(template) = args.value_of("build-metadata") {
+        config_builder.build_metadata(template.to_string());
+    }
     config_builder.branch(args.value_of("branch").unwrap_or("master").to_string());
+    config_builder.explicit_branch(args.is_present("branch"));
     config_builder.repository_path(repository_path.clone());
-    config_builder.signature(get_signature(repository_path.clone()));
+    let committer_sources = git::parse_committer_sources(args.value_of("committer-source").unwrap_or("env,git,github"))
+        .unwrap_or_else(|err| print_exit!("Invalid --committer-source: {}", err));
+    let default_committer = args.value_of("default-committer").map(|identity| {
+        utils::parse_identity(identity)
+            .unwrap_or_else(|| print_exit!("--default-committer expects `Name <email>`"))
+    });
+    let committer_sources = match default_committer {
+        // A given default is always the last resort, even if not listed explicitly.
+        Some(_) if !committer_sources.contains(&git::CommitterSource::Default) => {
+            let mut sources = committer_sources;
+            sources.push(git::CommitterSource::Default);
+            sources
+        },
+        _ => committer_sources
+    };
+    config_builder.signature(get_signature(repository_path.clone(), &committer_sources, default_committer.as_ref()));
     if let Some((user, repo)) = get_user_and_repo(&repository_path) {
         config_builder.user(user);
         config_builder.repository_name(repo);
@@ -290,9 +959,63 @@ fn assemble_configuration(args: ArgMatches) -> config::Config {
     if let Some(gh_token)  = get_github_token(&repository_path) {
         config_builder.gh_token(gh_token);
     }
-    if let Some(cargo_token) = get_cargo_token() {
+    if let Some(package_args) = args.values_of("package-arg") {
+        let package_args = package_args.map(|arg| arg.to_string()).collect::<Vec<_>>();
+        cargo::check_extra_args(&package_args)
+            .unwrap_or_else(|err| print_exit!("Invalid --package-arg: {}", err));
+        for arg in package_args {
+            config_builder.package_arg(arg);
+        }
+    }
+    if let Some(publish_args) = args.values_of("publish-arg") {
+        let publish_args = publish_args.map(|arg| arg.to_string()).collect::<Vec<_>>();
+        cargo::check_extra_args(&publish_args)
+            .unwrap_or_else(|err| print_exit!("Invalid --publish-arg: {}", err));
+        for arg in publish_args {
+            config_builder.publish_arg(arg);
+        }
+    }
+    let registry = args.value_of("registry");
+    if let Some(registry) = registry {
+        config_builder.registry(registry.to_string());
+    }
+    if let Some(cargo_token) = get_cargo_token(registry) {
         config_builder.cargo_token(cargo_token);
     }
+    if let Some(ssh_key) = get_ssh_key() {
+        config_builder.ssh_key(ssh_key);
+    }
+    if let Some(passphrase) = get_ssh_key_passphrase() {
+        config_builder.ssh_key_passphrase(passphrase);
+    }
+    if let Some(webhook_url) = get_discord_webhook() {
+        config_builder.discord_webhook(webhook_url);
+    }
+    if let Some(webhook_url) = get_teams_webhook() {
+        config_builder.teams_webhook(webhook_url);
+    }
+    if let Some(category) = args.value_of("discussion-category") {
+        config_builder.discussion_category(category.to_string());
+    }
+    if let Some(template) = args.value_of("milestone") {
+        config_builder.milestone(template.to_string());
+    }
+    if let Some(url) = args.value_of("webhook-url") {
+        config_builder.webhook_url(url.to_string());
+    }
+    if let Some(method) = args.value_of("webhook-method") {
+        config_builder.webhook_method(method.to_string());
+    }
+    if let Some(headers) = args.values_of("webhook-header") {
+        for header in headers {
+            let (name, value) = webhook::parse_header(header)
+                .unwrap_or_else(|err| print_exit!("{}", err));
+            config_builder.webhook_header(name, value);
+        }
+    }
+    if let Some(template) = args.value_of("webhook-body") {
+        config_builder.webhook_body(template.to_string());
+    }
     let repo = get_repo(&repository_path);
     match repo.find_remote("origin") {
         Ok(r) => config_builder.remote(Ok(r.name().unwrap().to_string())),
@@ -303,15 +1026,128 @@ fn assemble_configuration(args: ArgMatches) -> config::Config {
     config_builder.build()
 }
 
-fn main() {
-    env_logger::init().expect("Can't instantiate env logger");
-    println!("semantic.rs 🚀");
+/// How options set through `SEMANTIC_RS_*` variables relate to the command line.
+const ENV_OPTIONS: utils::EnvOptions<'static> = utils::EnvOptions {
+    shorts: &[("w", "write"), ("r", "release"), ("b", "branch"), ("p", "path"), ("v", "verbose")],
+    overrides: &[("dry-run", "no-dry-run")],
+    // Options that don't take a value, so their environment variable only switches them on.
+    flags: &["dry-run", "no-dry-run", "verbose"],
+};
+
+/// Whether the command line interface has a long option of this name.
+fn is_known_option(option: &str) -> bool {
+    match app().get_matches_from_safe(vec!["semantic-rs".to_owned(), format!("--{}", option)]) {
+        Err(ref err) if err.kind == ErrorKind::UnknownArgument => false,
+        _ => true,
+    }
+}
+
+/// The command line arguments, preceded by the options set through `SEMANTIC_RS_*` variables.
+fn args_with_env() -> Vec<String> {
+    let cli_args = env::args().collect::<Vec<_>>();
+    let vars = env::vars().collect::<Vec<_>>();
+
+    let (env_args, unknown) = utils::args_from_env(&vars, &cli_args[1..], &ENV_OPTIONS, is_known_option);
+    for name in unknown {
+        logger::warn(format!("Ignoring {}, it doesn't name an option", name));
+    }
+
+    let mut args = cli_args[..1].to_vec();
+    args.extend(env_args);
+    args.extend(cli_args[1..].iter().cloned());
+    args
+}
+
+/// Whether JSON output is asked for by `--log-format` or `SEMANTIC_RS_LOG_FORMAT`.
+///
+/// This is known before the arguments are parsed, so even warnings about them are JSON.
+fn json_log_format(args: &[String]) -> bool {
+    let from_args = args.iter().enumerate()
+        .filter_map(|(i, arg)| {
+            if arg == "--log-format" {
+                args.get(i + 1).map(|format| &format[..])
+            } else if arg.starts_with("--log-format=") {
+                Some(&arg["--log-format=".len()..])
+            } else {
+                None
+            }
+        })
+        .last();
+
+    match from_args {
+        Some(format) => format == "json",
+        None => env::var("SEMANTIC_RS_LOG_FORMAT").map(|format| format == "json").unwrap_or(false),
+    }
+}
 
+/// The log level for the number of `-v` flags: errors only without any,
+/// then warn, info, debug and trace for up to four.
+fn log_level(verbosity: u64) -> LogLevelFilter {
+    match verbosity {
+        0 => LogLevelFilter::Error,
+        1 => LogLevelFilter::Warn,
+        2 => LogLevelFilter::Info,
+        3 => LogLevelFilter::Debug,
+        _ => LogLevelFilter::Trace,
+    }
+}
+
+/// Sets up logging from `RUST_LOG` if it is set, otherwise from the `-v` flags.
+///
+/// With JSON output, the records of dependencies are formatted like our own messages.
+fn init_logger(verbosity: u64, json: bool) {
+    let mut builder = LogBuilder::new();
+    if json {
+        builder.format(|record| {
+            logger::json_line(&record.level().to_string().to_lowercase(), record.target(), &record.args().to_string())
+        });
+    }
+    match env::var("RUST_LOG") {
+        Ok(filters) => { builder.parse(&filters); },
+        Err(_) => { builder.filter(None, log_level(verbosity)); },
+    }
+    builder.init().expect("Can't instantiate env logger");
+}
 
-    let clap_args =  App::new("semantic-rs")
+/// The command line interface.
+fn app() -> App<'static, 'static> {
+    App::new("semantic-rs")
         .version(VERSION)
         .author("Jan Schulte <hello@unexpected-code> & Jan-Erik Rediger <janerik@fnordig.de>")
         .about("Crate publishing done right")
+        .arg(Arg::with_name("verbose")
+             .short("v")
+             .long("verbose")
+             .help("Show the logs of the libraries used, e.g. for git and HTTP; repeat for more: -v for warnings up to -vvvv for tracing. RUST_LOG takes precedence if set.")
+             .multiple(true))
+        .arg(Arg::with_name("log-format")
+             .long("log-format")
+             .help("The output format, either text or json. Also read from SEMANTIC_RS_LOG_FORMAT [default: text].")
+             .value_name("FORMAT")
+             .possible_values(&["text", "json"])
+             .takes_value(true))
+        .arg(Arg::with_name("ci")
+             .long("ci")
+             .help("Whether this runs on CI, which enables write mode: auto, yes or no. Detects GitHub Actions, GitLab CI, Travis CI, CircleCI and `CI` [default: auto].")
+             .value_name("CI")
+             .takes_value(true))
+        .arg(Arg::with_name("dry-run")
+             .long("dry-run")
+             .help("Only show what would be done. Overrides --write and CI detection.")
+             .conflicts_with("no-dry-run"))
+        .arg(Arg::with_name("no-dry-run")
+             .long("no-dry-run")
+             .help("Write changes, as --write=yes. Overrides --write and CI detection."))
+        .arg(Arg::with_name("committer-source")
+             .long("committer-source")
+             .help("Where to take the committer from, in order: env, git, github and default [default: env,git,github].")
+             .value_name("SOURCES")
+             .takes_value(true))
+        .arg(Arg::with_name("default-committer")
+             .long("default-committer")
+             .help("The committer to use if no other source provides one, as `Name <email>`.")
+             .value_name("IDENTITY")
+             .takes_value(true))
         .arg(Arg::with_name("write")
              .short("w")
              .long("write")
@@ -330,22 +1166,333 @@ fn main() {
              .help("The branch on which releases should happen. [default: master].")
              .value_name("BRANCH")
              .takes_value(true))
+        .arg(Arg::with_name("pull-request")
+             .long("pull-request")
+             .help("Commit the release to a separate branch and open a pull request against the release branch [default: no].")
+             .value_name("PULL_REQUEST_MODE")
+             .takes_value(true))
+        .arg(Arg::with_name("build-metadata")
+             .long("build-metadata")
+             .help("Append build metadata to the new version. {sha} and {build} are replaced by the commit hash and CI build number.")
+             .value_name("TEMPLATE")
+             .takes_value(true))
+        .arg(Arg::with_name("ignore-prereleases")
+             .long("ignore-prereleases")
+             .help("Ignore prerelease tags when looking for the last release [default: no].")
+             .value_name("IGNORE_PRERELEASES")
+             .takes_value(true))
+        .arg(Arg::with_name("since")
+             .long("since")
+             .help("Analyze commits and generate the changelog starting from this tag or revision [default: the last release].")
+             .value_name("REV")
+             .takes_value(true))
+        .arg(Arg::with_name("prerelease")
+             .long("prerelease")
+             .help("Mark the GitHub release as a prerelease [default: yes for prerelease versions, otherwise no].")
+             .value_name("PRERELEASE")
+             .takes_value(true))
+        .arg(Arg::with_name("only-path")
+             .long("only-path")
+             .help("Only consider commits touching this path (relative to the repository root) when deriving the version bump.")
+             .value_name("SUBPATH")
+             .takes_value(true))
+        .arg(Arg::with_name("registry")
+             .long("registry")
+             .help("Publish to this registry instead of crates.io. The token is read from CARGO_REGISTRIES_<NAME>_TOKEN.")
+             .value_name("REGISTRY")
+             .takes_value(true))
+        .arg(Arg::with_name("package-arg")
+             .long("package-arg")
+             .help("An extra argument passed to `cargo package`, e.g. --package-arg=--no-verify. Can be repeated.")
+             .value_name("ARG")
+             .takes_value(true)
+             .allow_hyphen_values(true)
+             .multiple(true)
+             .number_of_values(1))
+        .arg(Arg::with_name("publish-arg")
+             .long("publish-arg")
+             .help("An extra argument passed to `cargo publish`, e.g. --publish-arg=--no-verify. Can be repeated.")
+             .value_name("ARG")
+             .takes_value(true)
+             .allow_hyphen_values(true)
+             .multiple(true)
+             .number_of_values(1))
+        .arg(Arg::with_name("preset")
+             .long("preset")
+             .help("The commit message convention used to determine the version bump [default: conventionalcommits].")
+             .value_name("PRESET")
+             .possible_values(&["conventionalcommits", "angular", "atom"])
+             .takes_value(true))
+        .arg(Arg::with_name("zero-version")
+             .long("zero-version")
+             .help("How to bump versions below 1.0.0 [default: standard].")
+             .value_name("BEHAVIOR")
+             .possible_values(&["standard", "semver-default", "strict"])
+             .takes_value(true))
+        .arg(Arg::with_name("squash-bodies")
+             .long("squash-bodies")
+             .help("Also analyze every line in commit bodies as a commit subject, as in squash merges [default: no].")
+             .value_name("SQUASH_BODIES")
+             .takes_value(true))
+        .arg(Arg::with_name("ignore-merges")
+             .long("ignore-merges")
+             .help("Leave merge commits out of the analysis and the changelog, the merged commits are included on their own [default: yes].")
+             .value_name("IGNORE_MERGES")
+             .takes_value(true))
+        .arg(Arg::with_name("version-heading")
+             .long("version-heading")
+             .help("The changelog heading of a release. Supports {version}, {date}, {year}, {month} and {day} [default: v{version} ({date})].")
+             .value_name("TEMPLATE")
+             .takes_value(true))
+        .arg(Arg::with_name("date-utc-offset")
+             .long("date-utc-offset")
+             .help("The offset from UTC in hours used for dates in the version heading [default: 0].")
+             .value_name("HOURS")
+             .takes_value(true)
+             .allow_hyphen_values(true))
+        .arg(Arg::with_name("notes-from-changelog")
+             .long("notes-from-changelog")
+             .help("Use the section for the new version in this changelog file as GitHub release notes, instead of generating them.")
+             .value_name("FILE")
+             .takes_value(true))
+        .arg(Arg::with_name("version-file")
+             .long("version-file")
+             .help("Read and write the version in this plain file instead of Cargo.toml. Disables all cargo steps.")
+             .value_name("FILE")
+             .takes_value(true))
+        .arg(Arg::with_name("tag-message")
+             .long("tag-message")
+             .help("Annotate the release tag with this instead of the changelog. Supports {version} and {tag}.")
+             .value_name("TEMPLATE")
+             .takes_value(true))
+        .arg(Arg::with_name("sbom-command")
+             .long("sbom-command")
+             .help("Generate an SBOM with this shell command before the release commit. Supports {file} and {version}.")
+             .value_name("COMMAND")
+             .takes_value(true))
+        .arg(Arg::with_name("sbom-file")
+             .long("sbom-file")
+             .help("The file the SBOM command writes, committed with the release. Supports {version}.")
+             .value_name("FILE")
+             .takes_value(true))
+        .arg(Arg::with_name("skip-ci-token")
+             .long("skip-ci-token")
+             .help("Append this to the release commit message so CI does not build it [default: depends on the CI service].")
+             .value_name("TOKEN")
+             .takes_value(true))
+        .arg(Arg::with_name("http-timeout")
+             .long("http-timeout")
+             .help("Give up on requests to GitHub, crates.io and webhooks that stall for this many seconds [default: no limit].")
+             .value_name("SECONDS")
+             .takes_value(true))
+        .arg(Arg::with_name("command-timeout")
+             .long("command-timeout")
+             .help("Stop cargo fetch, package and publish if they take longer than this many seconds [default: no limit].")
+             .value_name("SECONDS")
+             .takes_value(true))
+        .arg(Arg::with_name("tag-wait-timeout")
+             .long("tag-wait-timeout")
+             .help("Seconds to wait for GitHub to show the pushed tag before creating the release [default: 30].")
+             .value_name("SECONDS")
+             .takes_value(true))
+        .arg(Arg::with_name("run-hooks")
+             .long("run-hooks")
+             .help("Commit and push with the git command line so git hooks run [default: no].")
+             .value_name("RUN_HOOKS")
+             .takes_value(true))
+        .arg(Arg::with_name("allow-dirty")
+             .long("allow-dirty")
+             .help("Release even if the working tree has unrelated changes [default: no].")
+             .value_name("ALLOW_DIRTY")
+             .takes_value(true))
+        .arg(Arg::with_name("strict-commits")
+             .long("strict-commits")
+             .help("Block the release if a commit doesn't follow the commit message format, instead of only warning [default: no].")
+             .value_name("STRICT_COMMITS")
+             .takes_value(true))
+        .arg(Arg::with_name("webhook-url")
+             .long("webhook-url")
+             .help("Send a JSON notification to this URL after releasing.")
+             .value_name("URL")
+             .takes_value(true))
+        .arg(Arg::with_name("webhook-method")
+             .long("webhook-method")
+             .help("The HTTP method used for the webhook notification [default: POST].")
+             .value_name("METHOD")
+             .takes_value(true))
+        .arg(Arg::with_name("webhook-header")
+             .long("webhook-header")
+             .help("An additional header for the webhook notification, as 'Name: value'. Can be given multiple times.")
+             .value_name("HEADER")
+             .takes_value(true)
+             .multiple(true)
+             .number_of_values(1))
+        .arg(Arg::with_name("webhook-body")
+             .long("webhook-body")
+             .help("The JSON body template for the webhook notification. {version}, {tag}, {bump} and {notes} are replaced.")
+             .value_name("TEMPLATE")
+             .takes_value(true))
+        .arg(Arg::with_name("pull-request-variable")
+             .long("pull-request-variable")
+             .help("An additional environment variable indicating a pull request build, as NAME or NAME=VALUE. Can be given multiple times.")
+             .value_name("VARIABLE")
+             .takes_value(true)
+             .multiple(true)
+             .number_of_values(1))
+        .arg(Arg::with_name("also-bump")
+             .long("also-bump")
+             .help("An additional file in which the current version following the package name is replaced by the new one and which is committed with the release. Can be given multiple times.")
+             .value_name("FILE")
+             .takes_value(true)
+             .multiple(true)
+             .number_of_values(1))
+        .arg(Arg::with_name("version-replace")
+             .long("version-replace")
+             .help("Replace the version in a file wherever a regex matches, as FILE=PATTERN or FILE=PATTERN=>REPLACEMENT. Without a replacement the first capture group is replaced, the replacement supports {version}. Can be given multiple times.")
+             .value_name("FILE=PATTERN")
+             .takes_value(true)
+             .multiple(true)
+             .number_of_values(1))
+        .arg(Arg::with_name("wait-for-index")
+             .long("wait-for-index")
+             .help("After publishing, wait up to this many seconds until crates.io serves the new version.")
+             .value_name("SECONDS")
+             .takes_value(true))
+        .arg(Arg::with_name("fetch-tags")
+             .long("fetch-tags")
+             .help("Fetch tags from origin before looking for the last release (default: yes if CI is set, otherwise no).")
+             .value_name("FETCH_TAGS")
+             .takes_value(true))
+        .arg(Arg::with_name("max-bump")
+             .long("max-bump")
+             .help("The largest allowed version bump, one of major, minor or patch [default: major].")
+             .value_name("BUMP")
+             .possible_values(&["major", "minor", "patch"])
+             .takes_value(true))
+        .arg(Arg::with_name("discussion-category")
+             .long("discussion-category")
+             .help("Announce the release as a GitHub discussion in this category.")
+             .value_name("CATEGORY")
+             .takes_value(true))
+        .arg(Arg::with_name("milestone")
+             .long("milestone")
+             .help("Close the GitHub milestone with this title after releasing. Supports {version}, {major}, {minor} and {patch}.")
+             .value_name("TITLE")
+             .takes_value(true))
+        .arg(Arg::with_name("push")
+             .long("push")
+             .help("Push the release commit and tag. With no, they are only created locally and GitHub steps are skipped [default: yes].")
+             .value_name("PUSH")
+             .takes_value(true))
+        .arg(Arg::with_name("skip")
+             .long("skip")
+             .help("Comma-separated release steps to skip: verify, package, push, github, cratesio, notify")
+             .value_name("STEPS")
+             .takes_value(true))
         .arg(Arg::with_name("path")
              .short("p")
              .long("path")
              .help("Specifies the repository path. [default: .]")
              .value_name("PATH")
              .takes_value(true))
-        .get_matches();
+        .subcommand(SubCommand::with_name("notes")
+            .about("Print the changelog between two revisions without releasing anything")
+            .arg(Arg::with_name("since")
+                 .long("since")
+                 .help("The tag or revision to start from.")
+                 .value_name("REV")
+                 .takes_value(true)
+                 .required(true))
+            .arg(Arg::with_name("to")
+                 .long("to")
+                 .help("The tag or revision to end at [default: HEAD].")
+                 .value_name("REV")
+                 .takes_value(true)))
+        .subcommand(SubCommand::with_name("config")
+            .about("Print the configuration resolved from the arguments and environment as JSON"))
+        .subcommand(SubCommand::with_name("preflight")
+            .about("Run only the preflight checks, exiting with 1 if any of them fails"))
+        .subcommand(SubCommand::with_name("github-release")
+            .about("Create the GitHub release for an already pushed tag, using the tag message as notes")
+            .arg(Arg::with_name("tag")
+                 .long("tag")
+                 .help("The tag to release [default: the latest release tag].")
+                 .value_name("TAG")
+                 .takes_value(true)))
+}
+
+fn main() {
+
+    let json = json_log_format(&env::args().collect::<Vec<_>>());
+    if json {
+        logger::use_json_format();
+    }
+
+    let clap_args = match app().get_matches_from_safe(args_with_env()) {
+        Ok(args) => args,
+        Err(ref err) if json && err.kind != ErrorKind::HelpDisplayed && err.kind != ErrorKind::VersionDisplayed => {
+            logger::stderr(&err.message);
+            process::exit(1);
+        },
+        Err(err) => err.exit(),
+    };
+
+    init_logger(clap_args.occurrences_of("verbose"), json);
+
+    if let Some(notes_args) = clap_args.subcommand_matches("notes") {
+        print_notes(&clap_args, notes_args);
+        process::exit(0);
+    }
+
+    let print_config = clap_args.subcommand_matches("config").is_some();
+    let preflight_only = clap_args.subcommand_matches("preflight").is_some();
+    let existing_tag = clap_args.subcommand_matches("github-release")
+        .map(|release_args| release_args.value_of("tag").map(|tag| tag.to_owned()));
 
     let config = assemble_configuration(clap_args);
+    if let Some(seconds) = config.http_timeout {
+        http::set_timeout(seconds);
+    }
+
+    if print_config {
+        logger::stdout(config.to_json().pretty().to_string());
+        process::exit(0);
+    }
+
+    logger::stdout("semantic.rs 🚀");
+
+    if preflight_only {
+        process::exit(if run_preflight_checks(&config) { 0 } else { 1 });
+    }
 
-    let branch = current_branch(&config.repository)
-        .unwrap_or_else(|| print_exit!("Could not determine current branch."));
+    if let Some(tag_name) = existing_tag {
+        release_existing_tag(&config, tag_name);
+        process::exit(0);
+    }
+
+    // CI logs stay plain, interactive runs get a check for every completed step.
+    if config.ci_provider.is_none() && logger::is_terminal() {
+        logger::use_progress();
+    }
+
+    if !config.write_mode {
+        logger::stdout(format!("Dry run ({}): nothing will be written or published", config.write_mode_reason));
+    } else if config.release_mode {
+        logger::stdout(format!("Write mode ({}): the release will be committed, tagged and published", config.write_mode_reason));
+    } else {
+        logger::stdout(format!("Write mode ({}): the release will be committed and tagged, but not published", config.write_mode_reason));
+    }
+
+    let branch = current_branch(&config)
+        .unwrap_or_else(|| print_exit!("Could not determine current branch: HEAD is detached. Check out the release branch first, or pass --branch to release the detached commit as that branch."));
+
+    if ci::is_pull_request(&config.pull_request_variables) {
+        logger::stdout("This is a pull request build. Skipping release on pull request.");
+        process::exit(0);
+    }
 
     if !is_release_branch(&branch, &config.branch) {
-        println!("Current branch is '{}', releases are only done from branch '{}'", branch, config.branch);
-        println!("No release done from a pull request either.");
+        logger::stdout(format!("Current branch is '{}', releases are only done from branch '{}'", branch, config.branch));
         process::exit(0);
     }
 
@@ -355,29 +1502,23 @@ fn main() {
     //The important bit is, if something's missing, we do not abort since the user can still do all
     //other things except publishing
 
-    logger::stdout("Performing preflight checks now");
-    let warnings = preflight::check(&config);
-
-    if warnings.is_empty() {
-        logger::stdout("Checks done. Everything is ok");
-    }
-
-    for warning in warnings {
-        logger::warn(format!(">> {}", warning));
+    if !run_preflight_checks(&config) {
+        process::exit(1);
     }
 
-    if config.release_mode && ci_env_set() {
+    // Waiting for the other jobs of a build matrix relies on the Travis API.
+    if config.release_mode && config.ci_provider == Some(ci::Provider::Travis) {
         let build_run = Build::from_env()
             .unwrap_or_else(|e| print_exit!("CI mode, but can't check other builds. Error: {:?}", e));
 
         if !build_run.is_leader() {
-            println!("Not the build leader. Nothing to do. Bye.");
+            logger::stdout("Not the build leader. Nothing to do. Bye.");
             process::exit(0);
         }
 
-        println!("I am the build leader. Waiting for other jobs to finish.");
+        logger::stdout("I am the build leader. Waiting for other jobs to finish.");
         match build_run.wait_for_others() {
-            Ok(()) => println!("Other jobs finished and succeeded. Doing my work now."),
+            Ok(()) => logger::stdout("Other jobs finished and succeeded. Doing my work now."),
             Err(travis_after_all::Error::FailedBuilds) => {
                 print_exit!("Some builds failed. Stopping here.");
             },
@@ -385,59 +1526,181 @@ fn main() {
         }
     }
 
-    let version = toml_file::read_from_file(&config.repository_path)
-        .unwrap_or_else(|err| print_exit!("Reading `Cargo.toml` failed: {:?}", err));
+    let version = match config.version_file {
+        Some(ref file) => version_files::read_version_file(&config.repository_path, file)
+            .unwrap_or_else(|err| print_exit!("Reading `{}` failed: {:?}", file, err)),
+        None => toml_file::read_from_file(&config.repository_path)
+            .unwrap_or_else(|err| print_exit!("Reading `Cargo.toml` failed: {:?}", err))
+    };
 
     let version = Version::parse(&version).expect("Not a valid version");
     logger::stdout(format!("Current version: {}", version.to_string()));
 
+    if config.fetch_tags && config.remote.is_ok() {
+        fetch_tags(&config);
+    }
+
     logger::stdout("Analyzing commits");
 
-    let bump = git::version_bump_since_latest(&config.repository);
+    if git::is_history_incomplete(&config) {
+        print_exit!("The repository is a shallow clone without any release tag, so the last release can't be determined.\n\
+                     Fetch the full history and all tags first, e.g. with `git fetch --unshallow --tags`.");
+    }
+
+    let bump = zero_version_bump(&config, &version, git::version_bump_since_latest(&config));
+    let bump = cap_bump(&config, bump);
     if config.write_mode {
         logger::stdout(format!("Commits analyzed. Bump will be {:?}", bump));
     } else {
         logger::stdout(format!("Commits analyzed. Bump would be {:?}", bump));
     }
-    let new_version = match version_bump(&version, bump) {
-        Some(new_version) => new_version.to_string(),
+    let (new_version, bump) = match git::release_as_since_latest(&config) {
+        Some(ref forced_version) if *forced_version <= version => {
+            print_exit!("The Release-As footer asks for {}, which is not newer than the current version {}", forced_version, version);
+        },
+        Some(forced_version) => {
+            logger::stdout(format!("Found a Release-As footer, releasing {}", forced_version));
+            let bump = analyzer::bump_between(&version, &forced_version);
+            (apply_build_metadata(&config, forced_version).to_string(), bump)
+        },
+        None => match version_bump(&version, bump) {
+            Some(new_version) => (apply_build_metadata(&config, new_version).to_string(), bump),
             None => {
                 logger::stdout("No version bump. Nothing to do.");
                 process::exit(0);
             }
+        }
     };
 
+    // The changelog covers the same commits as the analysis, e.g. skipping prereleases if configured.
+    let changelog_start = git::start_revision(&config)
+        .unwrap_or_else(|| format!("v{}", version));
+
     if !config.write_mode {
-        let changelog = generate_changelog(&config.repository_path, &version, &new_version);
+        match config.version_file {
+            Some(ref file) => print_version_file_diff(file, &version.to_string(), &new_version),
+            None => print_manifest_diff(&config.repository_path, &new_version)
+        }
+        print_version_replacement_diffs(&config, &version, &new_version);
+        let heading = version_heading(&config, &new_version);
+        print_changelog_diff(&config, &changelog_start, &heading);
+        let changelog = generate_changelog(&config, &changelog_start, &new_version, &heading);
         print_changelog(&changelog);
+        if let Some(file) = config.sbom_file_for(&new_version) {
+            logger::stdout(format!("Would generate SBOM {}", file));
+        }
     } else {
         logger::stdout(format!("New version: {}", new_version));
 
-        toml_file::write_new_version(&config.repository_path, &new_version)
-            .unwrap_or_else(|err| print_exit!("Writing `Cargo.toml` failed: {:?}", err));
+        if config.skips("verify") {
+            logger::warn("Skipping release verification");
+        } else {
+            verify_release(&config, &new_version);
+            logger::step_done("verify");
+        }
 
-        write_changelog(&config.repository_path, &version, &new_version);
-        package_crate(&config, &config.repository_path, &new_version);
+        match config.version_file {
+            Some(ref file) => {
+                version_files::write_version_file(&config.repository_path, file, &new_version)
+                    .unwrap_or_else(|err| print_exit!("Writing `{}` failed: {:?}", file, err));
+            },
+            None => {
+                toml_file::write_new_version(&config.repository_path, &new_version)
+                    .unwrap_or_else(|err| print_exit!("Writing `Cargo.toml` failed: {:?}", err));
+                toml_file::write_lockfile_version(&config.repository_path, &new_version)
+                    .unwrap_or_else(|err| print_exit!("Writing `Cargo.lock` failed: {:?}", err));
+            }
+        }
+        bump_additional_files(&config, &version, &new_version);
+        generate_sbom(&config, &new_version);
+        logger::step_done("prepare");
 
-        logger::stdout("Creating annotated git tag");
-        let tag_message = changelog::generate(&config.repository_path, &version.to_string(), &new_version)
+        let tag_name = format!("v{}", new_version);
+        let target_branch = config.target_branch(&tag_name);
+
+        let original_head = git::head_name(&config.repository)
+            .unwrap_or_else(|| print_exit!("No HEAD found for repository"));
+        let heading = version_heading(&config, &new_version);
+        write_changelog(&config, &changelog_start, &heading);
+        package_crate(&config, &config.repository_path, &target_branch, &new_version);
+        logger::step_done("package");
+
+        let tag_message = changelog::generate(&config.repository_path, &changelog_start, &heading, config.ignore_merges)
             .unwrap_or_else(|err| print_exit!("Can't generate changelog: {:?}", err));
 
-        let tag_name = format!("v{}", new_version);
-        git::tag(&config, &tag_name, &tag_message)
-            .unwrap_or_else(|err| print_exit!("Failed to create git tag: {:?}", err));
+        // A pull request can still be squashed or rebased, so its commit isn't tagged.
+        // `github-release` tags the merge commit instead.
+        if !config.pull_request_mode {
+            logger::stdout("Creating annotated git tag");
+            // The full changelog still goes to the GitHub release, only the annotation is shortened.
+            let annotation = match config.tag_message_template {
+                Some(ref template) => git::render_tag_message(template, &new_version, &tag_name),
+                None => tag_message.clone(),
+            };
+            git::tag(&config, &target_branch, &tag_name, &annotation)
+                .unwrap_or_else(|err| print_exit!("Failed to create git tag: {:?}", err));
+            logger::step_done("tag");
+        }
+
+        if config.release_mode && config.can_push() && !config.skips("push") {
+            let pushed_tag = if config.pull_request_mode { None } else { Some(&tag_name[..]) };
+            push_to_github(&config, &target_branch, pushed_tag);
+            logger::step_done("push");
+        }
+
+        // Committing with hooks checked out the pull request branch.
+        if config.pull_request_mode && config.run_hooks {
+            git::checkout(&config, &original_head)
+                .unwrap_or_else(|err| print_exit!("Checking out '{}' again failed: {:?}", original_head, err));
+        }
+
+        // With a pull request nothing is published until it is merged, see `github-release`.
+        let publish = config.release_mode && !config.pull_request_mode;
+
+        if config.release_mode && config.pull_request_mode && config.can_release_to_github() && !config.skips("github") {
+            open_pull_request(&config, &target_branch, &tag_message, &tag_name);
+            logger::stdout("Nothing is published until the pull request is merged");
+        }
+
+        let release_to_github = publish && config.can_release_to_github() && !config.skips("github");
+
+        if release_to_github {
+            release_on_github(&config, &new_version, &tag_message, &tag_name);
+        }
+
+        if release_to_github && github::can_release(&config) {
+            if let Some(ref template) = config.milestone {
+                close_milestone(&config, template, &new_version);
+            }
+            logger::step_done("github");
+        }
+
+        if publish && config.can_release_to_cratesio() && !config.skips("cratesio") {
+            release_on_cratesio(&config, &new_version);
+            logger::step_done("cratesio");
+            logger::stdout(format!("{} v{} is released. 🚀🚀🚀", config.repository_name.as_ref().unwrap(), new_version));
+        }
+
+        if release_to_github && github::can_release(&config) && !config.skips("notify") {
+            if let Some(ref category) = config.discussion_category {
+                announce_discussion(&config, category, &new_version, &tag_message, &tag_name);
+                logger::step_done("notify discussion");
+            }
+        }
 
-        if config.release_mode && config.can_push() {
-            push_to_github(&config, &tag_name);
+        if publish && config.can_notify_discord() && !config.skips("notify") {
+            notify_discord(&config, &tag_message, &tag_name);
+            logger::step_done("notify discord");
         }
 
-        if config.release_mode && config.can_release_to_github() {
-            release_on_github(&config, &tag_message, &tag_name);
+        if publish && config.can_notify_teams() && !config.skips("notify") {
+            notify_teams(&config, &tag_message, &tag_name, release_to_github && github::can_release(&config));
+            logger::step_done("notify teams");
         }
 
-        if config.release_mode && config.can_release_to_cratesio() {
-            release_on_cratesio(&config);
-            println!("{} v{} is released. 🚀🚀🚀", config.repository_name.unwrap(), new_version);
+        if publish && config.can_notify_webhook() && !config.skips("notify") {
+            notify_webhook(&config, &new_version, bump, &tag_message, &tag_name);
+            logger::step_done("notify webhook");
         }
     }
 }
@@ -1,20 +1,119 @@
 use config::Config;
+use ci;
+use cargo;
+use git2::BranchType;
 
-pub fn check(config: &Config) -> Vec<String> {
-    let mut warnings = vec!();
+/// Everything the preflight checks found, tagged with the area it concerns.
+///
+/// Warnings only disable parts of the release, errors stop it.
+pub struct PreflightReport {
+    pub warnings: Vec<(&'static str, String)>,
+    pub errors: Vec<(&'static str, String)>,
+}
+
+impl PreflightReport {
+    fn new() -> PreflightReport {
+        PreflightReport { warnings: vec![], errors: vec![] }
+    }
+
+    fn warn<S: Into<String>>(&mut self, area: &'static str, message: S) {
+        self.warnings.push((area, message.into()));
+    }
+
+    fn error<S: Into<String>>(&mut self, area: &'static str, message: S) {
+        self.errors.push((area, message.into()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.warnings.is_empty() && self.errors.is_empty()
+    }
+
+    pub fn has_errors(&self) -> bool {
+        !self.errors.is_empty()
+    }
+}
+
+/// Groups messages by their area, in the order the areas first appear.
+pub fn group_by_area<'a>(messages: &'a [(&'static str, String)]) -> Vec<(&'static str, Vec<&'a str>)> {
+    let mut groups: Vec<(&'static str, Vec<&'a str>)> = vec![];
+
+    for &(area, ref message) in messages {
+        match groups.iter().position(|&(a, _)| a == area) {
+            Some(idx) => groups[idx].1.push(&message[..]),
+            None => groups.push((area, vec![&message[..]])),
+        }
+    }
+
+    groups
+}
+
+pub fn check(config: &Config) -> PreflightReport {
+    let mut report = PreflightReport::new();
 
     if config.gh_token.is_none() {
-        warnings.push("The GH_TOKEN environment variable is not configured".into());
+        report.warn("github", "The GH_TOKEN environment variable is not configured");
+
+        if config.release_mode && config.pull_request_mode {
+            report.error("github", "Pull request mode needs GH_TOKEN to open the release pull request");
+        }
+    }
+
+    if config.is_cargo_project() && config.cargo_token.is_none() {
+        let variable = match config.registry {
+            Some(ref registry) => cargo::registry_token_variable(registry),
+            None => "CARGO_TOKEN".to_owned(),
+        };
+        report.warn("registry", format!("The {} environment variable is not configured. Cannot create release on {}",
+                                        variable, config.registry_name()));
+    }
+
+    if config.repository.is_shallow() {
+        report.warn("git", "The repository is a shallow clone. Commit analysis needs the full history and all tags, run `git fetch --unshallow --tags` first");
     }
 
-    if config.cargo_token.is_none() {
-        warnings.push("The CARGO_TOKEN environment variable is not configured. Cannot create release on crates.io".into());
+    if config.repository.head_detached().unwrap_or(false) {
+        check_detached_head(config, &mut report);
     }
 
     if let Err(ref err) = config.remote {
-        warnings.push(format!("Could not determine the origin remote url: {:?}", err));
-        warnings.push("semantic-rs can't push changes or create a release on GitHub".into());
+        report.warn("git", format!("Could not determine the origin remote url: {:?}", err));
+        report.warn("git", "semantic-rs can't push changes or create a release on GitHub");
+    }
+
+    report
+}
+
+/// A detached HEAD can only be released as the branch CI reports or `--branch` names,
+/// and only if the local branch of that name doesn't point elsewhere.
+fn check_detached_head(config: &Config, report: &mut PreflightReport) {
+    let branch = config.ci_provider.and_then(ci::branch)
+        .or_else(|| if config.explicit_branch { Some(config.branch.clone()) } else { None });
+
+    let branch = match branch {
+        Some(branch) => branch,
+        None => {
+            report.error("git", format!("HEAD is detached. Check out the release branch with `git checkout {}`, or pass --branch to release the detached commit as that branch", config.branch));
+            return;
+        }
+    };
+
+    let head = config.repository.head().ok().and_then(|head| head.target());
+    let local = config.repository.find_branch(&branch, BranchType::Local).ok()
+        .and_then(|local| local.get().target());
+
+    if local.is_some() && local != head {
+        report.error("git", format!("HEAD is detached and the local branch '{}' points to another commit. Check out '{}' and reset it to the commit to release", branch, branch));
     }
+}
+
+#[test]
+fn groups_messages_by_area() {
+    let messages = vec![
+        ("github", "no token".to_string()),
+        ("git", "shallow".to_string()),
+        ("github", "no remote".to_string()),
+    ];
 
-    warnings
+    let groups = group_by_area(&messages);
+    assert_eq!(vec![("github", vec!["no token", "no remote"]), ("git", vec!["shallow"])], groups);
 }
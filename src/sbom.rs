@@ -0,0 +1,40 @@
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+use cargo;
+
+/// Fills in `{version}` in the SBOM file name.
+pub fn render_file_name(template: &str, version: &str) -> String {
+    template.replace("{version}", version)
+}
+
+/// Fills in `{file}` and `{version}` in the generator command.
+fn render_command(command: &str, file: &str, version: &str) -> String {
+    command.replace("{file}", file).replace("{version}", version)
+}
+
+/// Runs the generator through the shell in the repository.
+///
+/// Fails if the command fails, times out or doesn't create the file.
+pub fn generate(repository_path: &str, command: &str, file: &str, version: &str, timeout: Option<Duration>) -> bool {
+    let mut shell = Command::new("sh");
+    shell
+        .arg("-c")
+        .arg(render_command(command, file, version))
+        .current_dir(repository_path);
+
+    cargo::run(&mut shell, timeout) && Path::new(repository_path).join(file).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_placeholders() {
+        let file = render_file_name("sbom/{version}.cdx.json", "1.2.0");
+        assert_eq!("sbom/1.2.0.cdx.json", file);
+        assert_eq!("cargo cyclonedx --output sbom/1.2.0.cdx.json",
+                   render_command("cargo cyclonedx --output {file}", &file, "1.2.0"));
+    }
+}
@@ -0,0 +1,62 @@
+use std::collections::BTreeMap;
+use rustc_serialize::json::Json;
+use error::Error;
+use http;
+use utils::truncate;
+
+// Teams rejects cards larger than 28KB, leave room for the rest of the card.
+const MAX_TEXT_LENGTH: usize = 20000;
+
+fn string(value: &str) -> Json {
+    Json::String(value.to_owned())
+}
+
+fn open_uri_action(name: &str, uri: &str) -> Json {
+    let mut target = BTreeMap::new();
+    target.insert("os".to_owned(), string("default"));
+    target.insert("uri".to_owned(), string(uri));
+
+    let mut action = BTreeMap::new();
+    action.insert("@type".to_owned(), string("OpenUri"));
+    action.insert("name".to_owned(), string(name));
+    action.insert("targets".to_owned(), Json::Array(vec![Json::Object(target)]));
+    Json::Object(action)
+}
+
+fn message_card(title: &str, text: &str, release_url: Option<&str>) -> Json {
+    let mut card = BTreeMap::new();
+    card.insert("@type".to_owned(), string("MessageCard"));
+    card.insert("@context".to_owned(), string("https://schema.org/extensions"));
+    card.insert("summary".to_owned(), string(title));
+    card.insert("title".to_owned(), string(title));
+    card.insert("text".to_owned(), string(&truncate(text, MAX_TEXT_LENGTH)));
+
+    if let Some(url) = release_url {
+        card.insert("potentialAction".to_owned(), Json::Array(vec![open_uri_action("View release", url)]));
+    }
+
+    Json::Object(card)
+}
+
+pub fn notify(webhook_url: &str, name: &str, tag_name: &str, release_notes: &str, release_url: Option<&str>) -> Result<(), Error> {
+    let title = format!("{} {} is released", name, tag_name);
+    let body = message_card(&title, release_notes, release_url).to_string();
+
+    http::post_json(webhook_url, &body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links_release_when_known() {
+        let card = message_card("semantic-rs v1.0.0 is released", "notes", Some("https://github.com/semantic-rs/semantic-rs/releases/tag/v1.0.0"));
+        let uri = card.find_path(&["potentialAction"]).and_then(|a| a.as_array()).and_then(|a| a[0].find_path(&["targets"]))
+            .and_then(|t| t.as_array()).and_then(|t| t[0].find("uri")).and_then(|u| u.as_string());
+        assert_eq!(Some("https://github.com/semantic-rs/semantic-rs/releases/tag/v1.0.0"), uri);
+
+        let card = message_card("semantic-rs v1.0.0 is released", "notes", None);
+        assert!(card.find("potentialAction").is_none());
+    }
+}
@@ -1,5 +1,5 @@
 use toml::Parser;
-use regex::Regex;
+use regex::{self, Regex};
 use std::io::prelude::*;
 use std::fs::File;
 use std::io::Error;
@@ -27,12 +27,34 @@ pub fn read_version(file: String) -> Option<String> {
     }
 }
 
+pub fn read_name(file: String) -> Option<String> {
+    let file_map = Parser::new(&file).parse().unwrap();
+    let package = match file_map.get("package") {
+        Some(package) => package,
+        None => return None
+    };
+    let name = package.as_table()
+        .unwrap()
+        .get("name");
+    match name {
+        Some(n) => Some(n.as_str().unwrap().into()),
+        None => None
+    }
+}
+
 pub fn file_with_new_version(file: String, new_version: &str) -> String {
-    let re = Regex::new(r#"version\s=\s"\d+\.\d+\.\d+""#).unwrap();
+    let re = Regex::new(r#"version\s=\s"\d+\.\d+\.\d+[^"]*""#).unwrap();
     let new_version = format!("version = \"{}\"", new_version);
     re.replace(&file, &new_version[..])
 }
 
+/// Updates the version of the crate's own entry in a `Cargo.lock`.
+pub fn lockfile_with_new_version(lockfile: String, name: &str, new_version: &str) -> String {
+    let re = Regex::new(&format!(r#"name = "{}"\s*\nversion = "[^"]*""#, regex::quote(name))).unwrap();
+    let new_entry = format!("name = \"{}\"\nversion = \"{}\"", name, new_version);
+    re.replace(&lockfile, &new_entry[..])
+}
+
 pub fn read_from_file(repository_path: &str) -> Result<String, TomlError> {
     let file_path = Path::new(&repository_path).join("Cargo.toml");
     let cargo_file = match read_cargo_toml(&file_path) {
@@ -46,14 +68,47 @@ pub fn read_from_file(repository_path: &str) -> Result<String, TomlError> {
     }
 }
 
+pub fn read_name_from_file(repository_path: &str) -> Result<String, TomlError> {
+    let file_path = Path::new(&repository_path).join("Cargo.toml");
+    let cargo_file = try!(read_cargo_toml(&file_path).map_err(TomlError::Io));
+
+    match read_name(cargo_file) {
+        Some(name) => Ok(name),
+        None => Err(TomlError::Parse("No name field found"))
+    }
+}
+
 pub fn write_new_version(repository_path: &str, new_version: &str) -> Result<(), Error> {
     let file_path = Path::new(&repository_path).join("Cargo.toml");
     let cargo_toml = try!(read_cargo_toml(&file_path));
     let new_cargo_toml = file_with_new_version(cargo_toml, new_version);
-    let mut handle = try!(OpenOptions::new().read(true).write(true).open(file_path));
+    let mut handle = try!(OpenOptions::new().write(true).truncate(true).open(file_path));
     handle.write_all(new_cargo_toml.as_bytes())
 }
 
+/// Returns the current `Cargo.toml` and how it would look with the new version.
+pub fn preview_new_version(repository_path: &str, new_version: &str) -> Result<(String, String), Error> {
+    let file_path = Path::new(&repository_path).join("Cargo.toml");
+    let cargo_toml = try!(read_cargo_toml(&file_path));
+    let new_cargo_toml = file_with_new_version(cargo_toml.clone(), new_version);
+    Ok((cargo_toml, new_cargo_toml))
+}
+
+/// Updates the crate's entry in `Cargo.lock`, if there is one.
+pub fn write_lockfile_version(repository_path: &str, new_version: &str) -> Result<(), TomlError> {
+    let lockfile_path = Path::new(&repository_path).join("Cargo.lock");
+    if !lockfile_path.exists() {
+        return Ok(());
+    }
+
+    let name = try!(read_name_from_file(repository_path));
+
+    let lockfile = try!(read_cargo_toml(&lockfile_path).map_err(TomlError::Io));
+    let new_lockfile = lockfile_with_new_version(lockfile, &name, new_version);
+    let mut handle = try!(OpenOptions::new().write(true).truncate(true).open(lockfile_path).map_err(TomlError::Io));
+    handle.write_all(new_lockfile.as_bytes()).map_err(TomlError::Io)
+}
+
 fn read_cargo_toml(file_path: &Path) -> Result<String, Error> {
     let mut handle = match File::open(file_path) {
         Ok(handle) => handle,
@@ -100,6 +155,28 @@ mod tests {
         assert_eq!(version_str, Some("0.1.0".into()));
     }
 
+    #[test]
+    fn read_name_of_package() {
+        assert_eq!(read_name(example_file()), Some("semantic-rs".into()));
+    }
+
+    #[test]
+    fn write_new_version_to_lockfile() {
+        let lockfile = "[[package]]
+name = \"semantic-rs\"
+version = \"0.1.0\"
+dependencies = [
+ \"term 0.2.14 (registry+https://github.com/rust-lang/crates.io-index)\",
+]
+
+[[package]]
+name = \"term\"
+version = \"0.2.14\"".to_string();
+
+        let new_lockfile = lockfile_with_new_version(lockfile.clone(), "semantic-rs", "0.2.0");
+        assert_eq!(new_lockfile, lockfile.replace("version = \"0.1.0\"", "version = \"0.2.0\""));
+    }
+
     #[test]
     fn read_file_without_version_number() {
         let version_str = read_version(example_file_without_version());
@@ -119,4 +196,11 @@ mod tests {
     toml = \"0.1\"".to_string();
         assert_eq!(new_toml_file, expected_file);
     }
+
+    #[test]
+    fn write_new_version_number_with_build_metadata() {
+        let new_toml_file = file_with_new_version(example_file(), "0.2.0+abc1234".into());
+        let new_toml_file = file_with_new_version(new_toml_file, "0.3.0".into());
+        assert_eq!(read_version(new_toml_file), Some("0.3.0".into()));
+    }
 }
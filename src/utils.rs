@@ -1,4 +1,5 @@
 use url::{Url, ParseError};
+use std::io;
 
 pub fn user_repo_from_url(url: &str) -> Result<(String, String), String> {
     let path = match Url::parse(url) {
@@ -44,6 +45,260 @@ pub fn user_repo_from_url(url: &str) -> Result<(String, String), String> {
     Ok((user, repo))
 }
 
+/// Renders a build metadata template.
+///
+/// `{sha}` is replaced by the given commit hash, `{build}` by the CI build number.
+pub fn render_build_metadata(template: &str, sha: &str, build: Option<&str>) -> Result<String, String> {
+    let metadata = template.replace("{sha}", sha);
+
+    if metadata.contains("{build}") {
+        match build {
+            Some(build) => Ok(metadata.replace("{build}", build)),
+            None => Err("Build metadata uses {build}, but no CI build number is available".into()),
+        }
+    } else {
+        Ok(metadata)
+    }
+}
+
+/// The civil date `(year, month, day)` of a unix timestamp, shifted by an offset from UTC in hours.
+pub fn date_from_timestamp(seconds: i64, utc_offset_hours: i64) -> (i64, u32, u32) {
+    // Days since 1970-01-01, converted following Howard Hinnant's `civil_from_days`.
+    let days = (seconds + utc_offset_hours * 3600) / 86400;
+
+    let z = days + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    (year, month as u32, day as u32)
+}
+
+/// Renders the version heading of a changelog entry.
+///
+/// `{version}` is replaced by the new version, `{date}` by the date as `YYYY-MM-DD`
+/// and `{year}`, `{month}` and `{day}` by its parts.
+pub fn render_version_heading(template: &str, version: &str, date: (i64, u32, u32)) -> String {
+    let (year, month, day) = date;
+
+    template
+        .replace("{version}", version)
+        .replace("{date}", &format!("{}-{:02}-{:02}", year, month, day))
+        .replace("{year}", &year.to_string())
+        .replace("{month}", &format!("{:02}", month))
+        .replace("{day}", &format!("{:02}", day))
+}
+
+/// Parses an identity given as `Name <email>`.
+pub fn parse_identity(identity: &str) -> Option<(String, String)> {
+    let open = match identity.find('<') { Some(i) => i, None => return None };
+    let close = match identity.rfind('>') { Some(i) => i, None => return None };
+    if close < open {
+        return None;
+    }
+
+    let name = identity[..open].trim();
+    let email = identity[open + 1..close].trim();
+    if name.is_empty() || email.is_empty() {
+        None
+    } else {
+        Some((name.to_owned(), email.to_owned()))
+    }
+}
+
+/// What `args_from_env` needs to know about the command line options.
+pub struct EnvOptions<'a> {
+    /// Options with a short name, as `(short, long)`.
+    pub shorts: &'a [(&'a str, &'a str)],
+    /// Pairs of options where giving either one on the command line overrides both.
+    pub overrides: &'a [(&'a str, &'a str)],
+    /// Options that don't take a value.
+    pub flags: &'a [&'a str],
+}
+
+/// Whether an option is given on the command line, by its long or short name.
+fn is_given(option: &str, cli_args: &[String], options: &EnvOptions) -> bool {
+    let long = format!("--{}", option);
+    let short = options.shorts.iter()
+        .find(|&&(_, long_name)| long_name == option)
+        .map(|&(short, _)| format!("-{}", short));
+
+    cli_args.iter().any(|arg| {
+        *arg == long || arg.starts_with(&format!("{}=", long)) ||
+            short.as_ref().map_or(false, |short| !arg.starts_with("--") && arg.starts_with(&short[..]))
+    })
+}
+
+/// Turns `SEMANTIC_RS_<OPTION>` environment variables into `--<option>` arguments.
+///
+/// `SEMANTIC_RS_TAG_WAIT_TIMEOUT=60` becomes `--tag-wait-timeout=60`. Options already given on
+/// the command line win. Flags without a value are only passed if their variable is truthy.
+/// Variables not naming a known option are returned separately instead of being passed on.
+pub fn args_from_env<F>(vars: &[(String, String)], cli_args: &[String], options: &EnvOptions, is_known: F) -> (Vec<String>, Vec<String>)
+    where F: Fn(&str) -> bool
+{
+    let mut vars = vars.iter()
+        .filter(|&&(ref name, _)| name.starts_with(ENV_PREFIX) && name.len() > ENV_PREFIX.len())
+        .collect::<Vec<_>>();
+    vars.sort();
+
+    let mut args = vec![];
+    let mut unknown = vec![];
+    for &(ref name, ref value) in vars {
+        let option = name[ENV_PREFIX.len()..].to_lowercase().replace('_', "-");
+        if !is_known(&option) {
+            unknown.push(name.clone());
+            continue;
+        }
+
+        let overridden = options.overrides.iter()
+            .filter(|&&(a, b)| a == option || b == option)
+            .any(|&(a, b)| is_given(a, cli_args, options) || is_given(b, cli_args, options));
+        if overridden || is_given(&option, cli_args, options) {
+            continue;
+        }
+
+        if options.flags.contains(&&option[..]) {
+            match &value.to_lowercase()[..] {
+                "yes" | "true" | "1" => args.push(format!("--{}", option)),
+                _ => {},
+            }
+        } else {
+            args.push(format!("--{}={}", option, value));
+        }
+    }
+
+    (args, unknown)
+}
+
+const ENV_PREFIX: &'static str = "SEMANTIC_RS_";
+
+/// Reads a secret from the `NAME` variable or else from the file `NAME_FILE` points to.
+///
+/// Secret mounts usually end the file with a newline, which is not part of the secret.
+pub fn secret_from_env<L, R>(name: &str, lookup: L, read_file: R) -> Result<Option<String>, String>
+    where L: Fn(&str) -> Option<String>,
+          R: Fn(&str) -> io::Result<String>
+{
+    if let Some(value) = lookup(name) {
+        return Ok(Some(value));
+    }
+
+    let file_variable = format!("{}_FILE", name);
+    match lookup(&file_variable) {
+        Some(path) => read_file(&path)
+            .map(|secret| Some(secret.trim_right_matches(|c| c == '\n' || c == '\r').to_owned()))
+            .map_err(|err| format!("Reading {} `{}` failed: {}", file_variable, path, err)),
+        None => Ok(None),
+    }
+}
+
+/// Shortens the text to at most `max_length` characters, marking the cut with an ellipsis.
+pub fn truncate(text: &str, max_length: usize) -> String {
+    if text.chars().count() <= max_length {
+        return text.into();
+    }
+
+    let mut truncated = text.chars().take(max_length - 1).collect::<String>();
+    truncated.push('…');
+    truncated
+}
+
+/// The number of unchanged lines shown around each change in a diff.
+const DIFF_CONTEXT: usize = 3;
+
+/// The `start,count` range of a hunk header, where an empty range starts at the line before.
+fn hunk_range(start: usize, count: usize) -> String {
+    if count == 0 {
+        format!("{},0", start)
+    } else {
+        format!("{},{}", start + 1, count)
+    }
+}
+
+/// A unified diff of two texts, based on the longest common subsequence of their lines.
+pub fn line_diff(old: &str, new: &str) -> String {
+    let old_lines = old.lines().collect::<Vec<_>>();
+    let new_lines = new.lines().collect::<Vec<_>>();
+
+    // Only the lines between the common prefix and suffix need the quadratic LCS,
+    // e.g. just the new entry when prepending to a long changelog.
+    let prefix = old_lines.iter().zip(&new_lines).take_while(|&(a, b)| a == b).count();
+    let suffix = old_lines[prefix..].iter().rev().zip(new_lines[prefix..].iter().rev())
+        .take_while(|&(a, b)| a == b)
+        .count();
+    let old_middle = &old_lines[prefix..old_lines.len() - suffix];
+    let new_middle = &new_lines[prefix..new_lines.len() - suffix];
+    let (n, m) = (old_middle.len(), new_middle.len());
+
+    // lcs[i][j] is the length of the longest common subsequence of old_middle[i..] and new_middle[j..].
+    let mut lcs = vec![vec![0; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old_middle[i] == new_middle[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                ::std::cmp::max(lcs[i + 1][j], lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ops = old_lines[..prefix].iter().map(|&line| (' ', line)).collect::<Vec<_>>();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && old_middle[i] == new_middle[j] {
+            ops.push((' ', old_middle[i]));
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            ops.push(('-', old_middle[i]));
+            i += 1;
+        } else {
+            ops.push(('+', new_middle[j]));
+            j += 1;
+        }
+    }
+    ops.extend(old_lines[old_lines.len() - suffix..].iter().map(|&line| (' ', line)));
+
+    // Changes closer than twice the context end up in the same hunk.
+    let mut hunks: Vec<(usize, usize)> = Vec::new();
+    for (idx, &(kind, _)) in ops.iter().enumerate() {
+        if kind == ' ' {
+            continue;
+        }
+        if let Some(hunk) = hunks.last_mut() {
+            if idx - hunk.1 <= 2 * DIFF_CONTEXT + 1 {
+                hunk.1 = idx;
+                continue;
+            }
+        }
+        hunks.push((idx, idx));
+    }
+
+    let mut diff = String::new();
+    for (first, last) in hunks {
+        let start = first.saturating_sub(DIFF_CONTEXT);
+        let end = ::std::cmp::min(last + DIFF_CONTEXT + 1, ops.len());
+
+        let old_start = ops[..start].iter().filter(|&&(kind, _)| kind != '+').count();
+        let new_start = ops[..start].iter().filter(|&&(kind, _)| kind != '-').count();
+        let old_count = ops[start..end].iter().filter(|&&(kind, _)| kind != '+').count();
+        let new_count = ops[start..end].iter().filter(|&&(kind, _)| kind != '-').count();
+
+        diff.push_str(&format!("@@ -{} +{} @@\n", hunk_range(old_start, old_count), hunk_range(new_start, new_count)));
+        for &(kind, line) in &ops[start..end] {
+            diff.push_str(&format!("{}{}\n", kind, line));
+        }
+    }
+
+    diff
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -85,6 +340,138 @@ mod test {
         }
     }
 
+    #[test]
+    fn renders_build_metadata() {
+        assert_eq!("abc1234", render_build_metadata("{sha}", "abc1234", None).unwrap());
+        assert_eq!("build.42.abc1234", render_build_metadata("build.{build}.{sha}", "abc1234", Some("42")).unwrap());
+        assert!(render_build_metadata("{build}", "abc1234", None).is_err());
+    }
+
+    #[test]
+    fn converts_timestamps_to_dates() {
+        assert_eq!((1970, 1, 1), date_from_timestamp(0, 0));
+        assert_eq!((2024, 1, 1), date_from_timestamp(19723 * 86400, 0));
+        assert_eq!((2024, 2, 29), date_from_timestamp(19782 * 86400 + 23 * 3600, 0));
+        assert_eq!((2024, 3, 1), date_from_timestamp(19782 * 86400 + 23 * 3600, 2));
+    }
+
+    #[test]
+    fn renders_version_headings() {
+        assert_eq!("v1.2.3", render_version_heading("v{version}", "1.2.3", (2024, 1, 1)));
+        assert_eq!("[1.2.3] - 2024-01-01", render_version_heading("[{version}] - {date}", "1.2.3", (2024, 1, 1)));
+        assert_eq!("1.2.3 (01.01.2024)", render_version_heading("{version} ({day}.{month}.{year})", "1.2.3", (2024, 1, 1)));
+    }
+
+    #[test]
+    fn parses_identities() {
+        assert_eq!(Some(("Release Bot".to_owned(), "bot@example.com".to_owned())),
+                   parse_identity("Release Bot <bot@example.com>"));
+        assert_eq!(None, parse_identity("Release Bot"));
+        assert_eq!(None, parse_identity("<bot@example.com>"));
+    }
+
+    const OPTIONS: EnvOptions<'static> = EnvOptions {
+        shorts: &[("b", "branch"), ("w", "write")],
+        overrides: &[("dry-run", "no-dry-run")],
+        flags: &["dry-run", "no-dry-run"],
+    };
+
+    fn env(vars: &[(&str, &str)]) -> Vec<(String, String)> {
+        vars.iter().map(|&(name, value)| (name.to_owned(), value.to_owned())).collect()
+    }
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    fn known(option: &str) -> bool {
+        ["tag-wait-timeout", "branch", "write", "dry-run", "no-dry-run"].contains(&option)
+    }
+
+    #[test]
+    fn maps_env_vars_to_args() {
+        let vars = env(&[
+            ("SEMANTIC_RS_TAG_WAIT_TIMEOUT", "60"),
+            ("SEMANTIC_RS_BRANCH", "main"),
+            ("SEMANTIC_RS_DRY_RUN", "true"),
+            ("SEMANTIC_RS_NO_DRY_RUN", "no"),
+            ("CI", "true"),
+        ]);
+
+        assert_eq!((args(&["--dry-run", "--tag-wait-timeout=60"]), vec![]),
+                   args_from_env(&vars, &args(&["--branch=develop"]), &OPTIONS, known));
+    }
+
+    #[test]
+    fn short_options_on_command_line_win() {
+        let vars = env(&[("SEMANTIC_RS_BRANCH", "main"), ("SEMANTIC_RS_WRITE", "yes")]);
+
+        assert_eq!((vec![], vec![]), args_from_env(&vars, &args(&["-b", "develop", "-wno"]), &OPTIONS, known));
+    }
+
+    #[test]
+    fn overriding_flag_on_command_line_wins() {
+        let vars = env(&[("SEMANTIC_RS_DRY_RUN", "true")]);
+
+        assert_eq!((vec![], vec![]), args_from_env(&vars, &args(&["--no-dry-run"]), &OPTIONS, known));
+    }
+
+    #[test]
+    fn skips_unknown_options() {
+        let vars = env(&[("SEMANTIC_RS_FOO", "bar"), ("SEMANTIC_RS_BRANCH", "main")]);
+
+        assert_eq!((args(&["--branch=main"]), args(&["SEMANTIC_RS_FOO"])),
+                   args_from_env(&vars, &[], &OPTIONS, known));
+    }
+
+    #[test]
+    fn reads_secret_from_variable_or_file() {
+        let vars = vec![("GH_TOKEN_FILE".to_owned(), "/run/secrets/gh".to_owned())];
+        let lookup = |name: &str| vars.iter().find(|&&(ref n, _)| n == name).map(|&(_, ref v)| v.clone());
+        let read = |path: &str| if path == "/run/secrets/gh" {
+            Ok("from-file\n".to_owned())
+        } else {
+            Err(io::Error::new(io::ErrorKind::NotFound, "not found"))
+        };
+
+        assert_eq!(Ok(Some("from-file".to_owned())), secret_from_env("GH_TOKEN", &lookup, &read));
+        assert_eq!(Ok(None), secret_from_env("CARGO_TOKEN", &lookup, &read));
+        assert_eq!(Ok(Some("direct".to_owned())),
+                   secret_from_env("GH_TOKEN", |name: &str| if name == "GH_TOKEN" { Some("direct".to_owned()) } else { lookup(name) }, &read));
+    }
+
+    #[test]
+    fn truncates_long_text() {
+        assert_eq!("short", truncate("short", 10));
+        assert_eq!("abcd…", truncate("abcdefghij", 5));
+    }
+
+    #[test]
+    fn diffs_changed_lines() {
+        let old = "[package]\nversion = \"0.1.0\"\n";
+        let new = "[package]\nversion = \"0.2.0\"\n";
+
+        assert_eq!("@@ -1,2 +1,2 @@\n [package]\n-version = \"0.1.0\"\n+version = \"0.2.0\"\n", line_diff(old, new));
+        assert_eq!("", line_diff(old, old));
+    }
+
+    #[test]
+    fn diffs_inserted_lines() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "new\na\nb\nc\nd\ne\nf\ng\nh\n";
+
+        assert_eq!("@@ -1,3 +1,4 @@\n+new\n a\n b\n c\n", line_diff(old, new));
+        assert_eq!("@@ -0,0 +1,1 @@\n+a\n", line_diff("", "a\n"));
+    }
+
+    #[test]
+    fn diffs_entry_prepended_to_long_file() {
+        let old = (0..100000).map(|i| format!("line {}\n", i)).collect::<String>();
+        let new = format!("## v1.0.0\n\n{}", old);
+
+        assert_eq!("@@ -1,3 +1,5 @@\n+## v1.0.0\n+\n line 0\n line 1\n line 2\n", line_diff(&old, &new));
+    }
+
     #[test]
     fn fail_some_urls() {
         let urls = [
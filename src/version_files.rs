@@ -0,0 +1,178 @@
+use std::fs::File;
+use std::io::prelude::*;
+use std::io::{Error, ErrorKind};
+use std::path::Path;
+use regex::{self, Captures, Regex};
+
+/// A version to rewrite wherever a pattern matches in a file.
+///
+/// Without a replacement, only the first capture group of each match, or the whole
+/// match if there is none, is replaced by the new version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VersionReplacement {
+    pub file: String,
+    pub pattern: String,
+    pub replacement: Option<String>,
+}
+
+/// Parses `FILE=PATTERN` or `FILE=PATTERN=>REPLACEMENT` as given on the command line.
+pub fn parse_version_replacement(spec: &str) -> Result<VersionReplacement, String> {
+    let mut parts = spec.splitn(2, '=');
+    let file = parts.next().unwrap_or("");
+    let rest = match parts.next() {
+        Some(rest) if !file.is_empty() && !rest.is_empty() => rest,
+        _ => return Err(format!("`{}` is not of the form FILE=PATTERN or FILE=PATTERN=>REPLACEMENT", spec)),
+    };
+
+    let (pattern, replacement) = match rest.rfind("=>") {
+        Some(idx) => (&rest[..idx], Some(rest[idx + 2..].to_owned())),
+        None => (rest, None),
+    };
+
+    if let Err(err) = Regex::new(pattern) {
+        return Err(format!("Invalid pattern `{}`: {}", pattern, err));
+    }
+
+    Ok(VersionReplacement {
+        file: file.to_owned(),
+        pattern: pattern.to_owned(),
+        replacement: replacement,
+    })
+}
+
+/// Rewrites every match of the pattern, or returns `None` if it doesn't match at all.
+pub fn content_with_replacement(content: &str, replacement: &VersionReplacement, new_version: &str) -> Option<String> {
+    let re = Regex::new(&replacement.pattern).expect("Pattern was validated when parsing");
+    if !re.is_match(content) {
+        return None;
+    }
+
+    let new_content = re.replace_all(content, |caps: &Captures| {
+        if let Some(ref template) = replacement.replacement {
+            return template.replace("{version}", new_version);
+        }
+
+        let whole = caps.at(0).unwrap_or("");
+        match (caps.pos(0), caps.pos(1)) {
+            (Some((start, _)), Some((group_start, group_end))) => {
+                format!("{}{}{}", &whole[..group_start - start], new_version, &whole[group_end - start..])
+            },
+            _ => new_version.to_owned(),
+        }
+    });
+
+    Some(new_content)
+}
+
+/// Reads a file relative to the repository and returns it with and without the replacement applied.
+pub fn preview_replacement(repository_path: &str, replacement: &VersionReplacement, new_version: &str) -> Result<(String, String), Error> {
+    let file_path = Path::new(repository_path).join(&replacement.file);
+
+    let mut content = String::new();
+    try!(try!(File::open(&file_path)).read_to_string(&mut content));
+
+    match content_with_replacement(&content, replacement, new_version) {
+        Some(new_content) => Ok((content, new_content)),
+        None => Err(Error::new(ErrorKind::InvalidData,
+                               format!("`{}` doesn't match `{}`", replacement.file, replacement.pattern))),
+    }
+}
+
+/// Rewrites the version in a file relative to the repository using a pattern.
+pub fn write_replacement(repository_path: &str, replacement: &VersionReplacement, new_version: &str) -> Result<(), Error> {
+    let (_, new_content) = try!(preview_replacement(repository_path, replacement, new_version));
+
+    let file_path = Path::new(repository_path).join(&replacement.file);
+    let mut handle = try!(File::create(&file_path));
+    handle.write_all(new_content.as_bytes())
+}
+
+/// The replacement for a file bumped without a pattern.
+///
+/// Only the old version following the package name on the same line is replaced,
+/// e.g. in `name = "1.0.0"`, so other dependencies pinned to the same version are kept.
+pub fn package_version_replacement(file: &str, name: &str, old_version: &str) -> VersionReplacement {
+    VersionReplacement {
+        file: file.to_owned(),
+        pattern: format!(r"{}\b[^\n]*?[^0-9.]({})\b", regex::quote(name), regex::quote(old_version)),
+        replacement: None,
+    }
+}
+
+/// The version stored in a plain version file: its first non-empty line.
+pub fn parse_version_file(content: &str) -> Option<String> {
+    content.lines()
+        .map(|line| line.trim())
+        .find(|line| !line.is_empty())
+        .map(|line| line.to_string())
+}
+
+/// Reads the current version from a plain version file, relative to the repository.
+pub fn read_version_file(repository_path: &str, file: &str) -> Result<String, Error> {
+    let file_path = Path::new(repository_path).join(file);
+
+    let mut content = String::new();
+    try!(try!(File::open(&file_path)).read_to_string(&mut content));
+
+    parse_version_file(&content)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, format!("`{}` contains no version", file)))
+}
+
+/// Replaces the content of a plain version file with the new version.
+pub fn write_version_file(repository_path: &str, file: &str, new_version: &str) -> Result<(), Error> {
+    let file_path = Path::new(repository_path).join(file);
+
+    let mut handle = try!(File::create(&file_path));
+    handle.write_all(format!("{}\n", new_version).as_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_package_version_only() {
+        let readme = "semantic-rs = \"1.0.0\"\nserde = \"1.0.0\"\n![semantic-rs](https://img.shields.io/badge/semantic--rs-v1.0.0-blue)";
+        let expected = "semantic-rs = \"1.1.0\"\nserde = \"1.0.0\"\n![semantic-rs](https://img.shields.io/badge/semantic--rs-v1.1.0-blue)";
+        let replacement = package_version_replacement("README.md", "semantic-rs", "1.0.0");
+        assert_eq!(Some(expected.to_owned()), content_with_replacement(readme, &replacement, "1.1.0"));
+
+        assert_eq!(None, content_with_replacement("serde = \"1.0.0\"\n", &replacement, "1.1.0"));
+    }
+
+    #[test]
+    fn parses_replacements() {
+        let replacement = parse_version_replacement("Chart.yaml=appVersion: \"(.*)\"").unwrap();
+        assert_eq!("Chart.yaml", replacement.file);
+        assert_eq!("appVersion: \"(.*)\"", replacement.pattern);
+        assert_eq!(None, replacement.replacement);
+
+        let replacement = parse_version_replacement("Dockerfile=ARG VERSION=.*=>ARG VERSION={version}").unwrap();
+        assert_eq!("ARG VERSION=.*", replacement.pattern);
+        assert_eq!(Some("ARG VERSION={version}".to_owned()), replacement.replacement);
+
+        assert!(parse_version_replacement("Chart.yaml").is_err());
+        assert!(parse_version_replacement("Chart.yaml=version: (").is_err());
+    }
+
+    #[test]
+    fn replaces_capture_group_or_with_template() {
+        let chart = "version: 0.3.0\nappVersion: \"1.0.0\"\n";
+        let group = parse_version_replacement("Chart.yaml=appVersion: \"(.*)\"").unwrap();
+        assert_eq!(Some("version: 0.3.0\nappVersion: \"1.1.0\"\n".to_owned()),
+                   content_with_replacement(chart, &group, "1.1.0"));
+
+        let template = parse_version_replacement("Chart.yaml=appVersion: .*=>appVersion: v{version}").unwrap();
+        assert_eq!(Some("version: 0.3.0\nappVersion: v1.1.0\n".to_owned()),
+                   content_with_replacement(chart, &template, "1.1.0"));
+
+        let missing = parse_version_replacement("Chart.yaml=image: (.*)").unwrap();
+        assert_eq!(None, content_with_replacement(chart, &missing, "1.1.0"));
+    }
+
+    #[test]
+    fn reads_first_non_empty_line() {
+        assert_eq!(Some("1.2.3".to_string()), parse_version_file("\n  1.2.3  \n"));
+        assert_eq!(None, parse_version_file("\n\n"));
+    }
+}
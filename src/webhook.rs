@@ -0,0 +1,56 @@
+use hyper::method::Method;
+use rustc_serialize::json::Json;
+use commit_analyzer::CommitType;
+use error::Error;
+use http;
+
+pub const DEFAULT_BODY: &'static str = r#"{"version": "{version}", "tag": "{tag}", "bump": "{bump}", "notes": "{notes}"}"#;
+
+/// Escapes a value so it can be placed inside a JSON string literal.
+fn escape(value: &str) -> String {
+    let quoted = Json::String(value.into()).to_string();
+    quoted[1..quoted.len() - 1].into()
+}
+
+/// Fills in the `{version}`, `{tag}`, `{bump}` and `{notes}` placeholders of a JSON body template.
+pub fn render_body(template: &str, version: &str, tag_name: &str, bump: CommitType, release_notes: &str) -> String {
+    template
+        .replace("{version}", &escape(version))
+        .replace("{tag}", &escape(tag_name))
+        .replace("{bump}", bump.as_str())
+        .replace("{notes}", &escape(release_notes))
+}
+
+/// Parses a `Name: value` header.
+pub fn parse_header(header: &str) -> Result<(String, String), String> {
+    let mut parts = header.splitn(2, ':');
+    match (parts.next(), parts.next()) {
+        (Some(name), Some(value)) if !name.trim().is_empty() => {
+            Ok((name.trim().into(), value.trim().into()))
+        },
+        _ => Err(format!("'{}' is not a valid header, expected 'Name: value'", header))
+    }
+}
+
+pub fn notify(url: &str, method: &str, headers: &[(String, String)], body: &str) -> Result<(), Error> {
+    let method = method.to_uppercase().parse().unwrap_or(Method::Post);
+    http::send_json(method, url, headers, body)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_escaped_placeholders() {
+        let body = render_body(DEFAULT_BODY, "1.0.0", "v1.0.0", CommitType::Minor, "Features\n* \"quoted\"");
+        assert_eq!(r#"{"version": "1.0.0", "tag": "v1.0.0", "bump": "minor", "notes": "Features\n* \"quoted\""}"#, body);
+    }
+
+    #[test]
+    fn parses_headers() {
+        assert_eq!(("X-Token".to_owned(), "abc: 123".to_owned()), parse_header("X-Token: abc: 123").unwrap());
+        assert!(parse_header("no header").is_err());
+        assert!(parse_header(": value").is_err());
+    }
+}
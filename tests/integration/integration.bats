@@ -3,6 +3,11 @@
 setup() {
   cd $WORKSPACE
   unset CI
+  unset GITHUB_ACTIONS
+  unset GITLAB_CI
+  unset TRAVIS
+  unset CIRCLECI
+  unset TF_BUILD
   unset TRAVIS_PULL_REQUEST
   unset TRAVIS_BRANCH
   unset TRAVIS_BUILD_ID
@@ -142,6 +147,47 @@ setup_dirs() {
   unset GIT_COMMITTER_EMAIL
 }
 
+@test "Runs git hooks only when asked to" {
+  cp -a "$BATS_TEST_DIRNAME/fixtures/next-minor" without-hooks
+  cp -a "$BATS_TEST_DIRNAME/fixtures/next-minor" with-hooks
+
+  cd without-hooks
+  setup_dirs
+  printf '#!/bin/sh\ntouch hook-ran\n' > .git/hooks/pre-commit
+  chmod +x .git/hooks/pre-commit
+
+  semantic-rs --write=yes --release=no
+  [ ! -f hook-ran ]
+
+  cd ../with-hooks
+  setup_dirs
+  printf '#!/bin/sh\ntouch hook-ran\n' > .git/hooks/pre-commit
+  chmod +x .git/hooks/pre-commit
+
+  semantic-rs --write=yes --release=no --run-hooks=yes
+  [ -f hook-ran ]
+
+  run git log --oneline --format=format:%s
+  [ "${lines[0]}" = "Bump version to 1.1.0" ]
+}
+
+@test "Runs git hooks on a detached HEAD" {
+  cp -a "$BATS_TEST_DIRNAME/fixtures/next-minor" detached-with-hooks
+
+  cd detached-with-hooks
+  setup_dirs
+  printf '#!/bin/sh\ntouch hook-ran\n' > .git/hooks/pre-commit
+  chmod +x .git/hooks/pre-commit
+  git checkout --quiet --detach
+
+  semantic-rs --write=yes --release=no --run-hooks=yes --branch=master
+  [ -f hook-ran ]
+
+  run git log --oneline --format=format:%s master
+  [ "${lines[0]}" = "Bump version to 1.1.0" ]
+  [ "$(git rev-parse 'v1.1.0^{commit}')" = "$(git rev-parse master)" ]
+}
+
 @test "Does not run when on wrong branch" {
   cd wrong-branch
   setup_dirs